@@ -5,7 +5,7 @@ use web_sys::HtmlCanvasElement;
 use yew::services::{RenderService, Task};
 use yew::{html, Component, ComponentLink, Html, NodeRef, Properties, ShouldRender};
 
-use shared::game::{GameState, Visibility};
+use shared::game::{GameStateView, Geometry, Visibility};
 use shared::message::{ClientMessage, GameAction};
 
 use crate::game_view::GameView;
@@ -160,6 +160,15 @@ impl Component for Board {
         let edge_size = self.edge_size as f64;
         let width = self.width as f64 - (edge_size);
         let height = self.height as f64 - (edge_size);
+        let cell = width / game.size.0.max(game.size.1) as f64;
+        // Pixel go coordinates go one past the board.
+        let extra = game.mods.pixel as u32;
+        let links = game
+            .mods
+            .geometry
+            .links(game.size.0 as u32, game.size.1 as u32)
+            .ok()
+            .flatten();
         let mouse_to_coord = |mut p: (f64, f64)| -> Option<(u32, u32)> {
             if p.0 < edge_size || p.1 < edge_size || p.0 > width || p.1 > height {
                 return None;
@@ -167,16 +176,19 @@ impl Component for Board {
 
             p.0 -= edge_size;
             p.1 -= edge_size;
-            Some(match game.mods.pixel {
-                true => (
-                    (p.0 / (width / game.size.0 as f64) + 0.5) as u32,
-                    (p.1 / (width / game.size.1 as f64) + 0.5) as u32,
-                ),
-                false => (
-                    (p.0 / (width / game.size.0 as f64)) as u32,
-                    (p.1 / (width / game.size.1 as f64)) as u32,
-                ),
-            })
+            let coord = match game.mods.pixel {
+                true => ((p.0 / cell + 0.5) as u32, (p.1 / cell + 0.5) as u32),
+                false => ((p.0 / cell) as u32, (p.1 / cell) as u32),
+            };
+            // The shorter side of the board doesn't fill the canvas.
+            if coord.0 >= game.size.0 as u32 + extra || coord.1 >= game.size.1 as u32 + extra {
+                return None;
+            }
+            // Graph boards leave out some points.
+            if links.as_ref().is_some_and(|links| !links.contains(coord)) {
+                return None;
+            }
+            Some(coord)
         };
 
         match msg {
@@ -249,9 +261,9 @@ impl Board {
             None => &game.board_visibility,
         };
 
-        // TODO: actually handle non-square boards
-        let board_size = game.size.0 as usize;
-        let size = (canvas.width() as f64 - 2.0 * edge_size) / board_size as f64;
+        let (board_width, board_height) = (game.size.0 as usize, game.size.1 as usize);
+        // Cells are square and sized for the longer side.
+        let size = (canvas.width() as f64 - 2.0 * edge_size) / board_width.max(board_height) as f64;
         let turn = game.seats[game.turn as usize].1;
 
         let draw_stone =
@@ -286,30 +298,63 @@ impl Board {
         context.set_stroke_style(&JsValue::from_str("#000000"));
         context.set_fill_style(&JsValue::from_str("#000000"));
 
-        for y in 0..game.size.1 {
-            context.begin_path();
-            context.move_to(edge_size + size * 0.5, edge_size + (y as f64 + 0.5) * size);
-            context.line_to(
-                edge_size + size * (board_size as f64 - 0.5),
-                edge_size + (y as f64 + 0.5) * size,
-            );
-            context.stroke();
-        }
+        let links = game
+            .mods
+            .geometry
+            .links(board_width as u32, board_height as u32)
+            .ok()
+            .flatten();
+        if let Some(links) = &links {
+            // Lines join neighbours next to each other on the board. The ones joined across
+            // the edges of a cube are too far apart to draw.
+            let center = |(x, y): (u32, u32)| {
+                (
+                    edge_size + (x as f64 + 0.5) * size,
+                    edge_size + (y as f64 + 0.5) * size,
+                )
+            };
+            for idx in 0..board_width * board_height {
+                let point = ((idx % board_width) as u32, (idx / board_width) as u32);
+                for &n in links.neighbours(point) {
+                    let (dx, dy) = (n.0 as i32 - point.0 as i32, n.1 as i32 - point.1 as i32);
+                    if (n.1, n.0) < (point.1, point.0) || dx.abs() > 1 || dy.abs() > 1 {
+                        continue;
+                    }
+                    context.begin_path();
+                    let (x, y) = center(point);
+                    context.move_to(x, y);
+                    let (x, y) = center(n);
+                    context.line_to(x, y);
+                    context.stroke();
+                }
+            }
+        } else {
+            for y in 0..game.size.1 {
+                context.begin_path();
+                context.move_to(edge_size + size * 0.5, edge_size + (y as f64 + 0.5) * size);
+                context.line_to(
+                    edge_size + size * (board_width as f64 - 0.5),
+                    edge_size + (y as f64 + 0.5) * size,
+                );
+                context.stroke();
+            }
 
-        for x in 0..game.size.0 {
-            context.begin_path();
-            context.move_to(edge_size + (x as f64 + 0.5) * size, edge_size + size * 0.5);
-            context.line_to(
-                edge_size + (x as f64 + 0.5) * size,
-                edge_size + size * (board_size as f64 - 0.5),
-            );
-            context.stroke();
+            for x in 0..game.size.0 {
+                context.begin_path();
+                context.move_to(edge_size + (x as f64 + 0.5) * size, edge_size + size * 0.5);
+                context.line_to(
+                    edge_size + (x as f64 + 0.5) * size,
+                    edge_size + size * (board_height as f64 - 0.5),
+                );
+                context.stroke();
+            }
         }
 
         // Starpoints /////////////////////////////////////////////////////////
 
-        let points: &[(u8, u8)] = match game.size.0 {
-            19 => &[
+        let points: &[(u8, u8)] = match game.size {
+            _ if game.mods.geometry != Geometry::Square => &[],
+            (19, 19) => &[
                 (3, 3),
                 (9, 3),
                 (15, 3),
@@ -320,8 +365,8 @@ impl Board {
                 (9, 15),
                 (15, 15),
             ],
-            13 => &[(3, 3), (9, 3), (6, 6), (3, 9), (9, 9)],
-            9 => &[(4, 4)],
+            (13, 13) => &[(3, 3), (9, 3), (6, 6), (3, 9), (9, 9)],
+            (9, 9) => &[(4, 4)],
             _ => &[],
         };
         for &(x, y) in points {
@@ -343,7 +388,7 @@ impl Board {
             context.fill_text(&text, from_edge, edge_size + y as f64 * size + 2.0)?;
             context.fill_text(
                 &text,
-                canvas.width() as f64 - from_edge,
+                2.0 * edge_size + board_width as f64 * size - from_edge,
                 edge_size + y as f64 * size + 2.0,
             )?;
         }
@@ -352,17 +397,13 @@ impl Board {
         context.set_text_baseline("baseline");
 
         for x in 0..game.size.0 {
-            let letter = ('A'..'I')
-                .chain('J'..'Z')
-                .nth(x as usize)
-                .unwrap()
-                .to_string();
+            let letter = column_name(x as usize);
             let x = x as f64 + 0.5;
             context.fill_text(&letter, edge_size + x as f64 * size, from_edge)?;
             context.fill_text(
                 &letter,
                 edge_size + x as f64 * size,
-                canvas.height() as f64 - from_edge,
+                2.0 * edge_size + board_height as f64 * size - from_edge,
             )?;
         }
 
@@ -403,8 +444,8 @@ impl Board {
         // Board stones ///////////////////////////////////////////////////////
 
         for (idx, &color) in board.iter().enumerate() {
-            let x = idx % board_size;
-            let y = idx / board_size;
+            let x = idx % board_width;
+            let y = idx / board_width;
 
             let visible = board_visibility
                 .as_ref()
@@ -424,8 +465,8 @@ impl Board {
         // Hidden stones //////////////////////////////////////////////////////
 
         for (idx, &colors) in board_visibility.iter().flatten().enumerate() {
-            let x = idx % board_size;
-            let y = idx / board_size;
+            let x = idx % board_width;
+            let y = idx / board_width;
 
             let colors = Visibility::from_value(colors);
 
@@ -445,7 +486,7 @@ impl Board {
 
         let last_stone = match (&game.state, &game.history) {
             (_, Some(h)) => h.last_stone.as_ref(),
-            (GameState::Play(state), _) => state.last_stone.as_ref(),
+            (GameStateView::Play(state), _) => state.last_stone.as_ref(),
             _ => None,
         };
 
@@ -465,11 +506,40 @@ impl Board {
             }
         }
 
+        // Ko marker ////////////////////////////////////////////////////////////
+
+        let ko_point = match (&game.state, &game.history) {
+            (_, Some(h)) => h.ko_point,
+            (GameStateView::Play(state), _) => state.ko_point,
+            _ => None,
+        };
+
+        if let Some((x, y)) = ko_point {
+            context.set_stroke_style(&JsValue::from_str(dead_mark_color[1]));
+            context.set_line_width(2.0);
+            context.stroke_rect(
+                edge_size + (x as f64 + 0.3) * size,
+                edge_size + (y as f64 + 0.3) * size,
+                0.4 * size,
+                0.4 * size,
+            );
+        }
+
         // States /////////////////////////////////////////////////////////////
 
         match &game.state {
-            GameState::Scoring(scoring) | GameState::Done(scoring) => {
+            GameStateView::Scoring(scoring) | GameStateView::Done(scoring) => {
                 for group in &scoring.groups {
+                    if group.seki {
+                        context.set_line_width(2.0);
+                        context.set_stroke_style(&JsValue::from_str(
+                            dead_mark_color[group.team.0 as usize - 1],
+                        ));
+                        for &(x, y) in &group.points {
+                            draw_stone((x as _, y as _), size / 4., false, true)?;
+                        }
+                    }
+
                     if group.alive {
                         continue;
                     }
@@ -509,8 +579,8 @@ impl Board {
                 }
 
                 for (idx, &color) in scoring.points.points.iter().enumerate() {
-                    let x = (idx % board_size) as f64;
-                    let y = (idx / board_size) as f64;
+                    let x = (idx % board_width) as f64;
+                    let y = (idx / board_width) as f64;
 
                     if color.is_empty() {
                         continue;
@@ -541,3 +611,11 @@ impl Board {
         Ok(())
     }
 }
+
+/// Column letters skip I. Boards wider than 25 continue with AA, BB and so on.
+fn column_name(x: usize) -> String {
+    let letters = ('A'..'I').chain('J'..='Z').collect::<Vec<_>>();
+    letters[x % letters.len()]
+        .to_string()
+        .repeat(x / letters.len() + 1)
+}
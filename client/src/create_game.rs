@@ -1,4 +1,3 @@
-use web_sys::HtmlSelectElement;
 use yew::prelude::*;
 
 use crate::game_view::Profile;
@@ -24,26 +23,40 @@ pub struct CreateGameView {
     seats: Vec<u8>,
     /// komi = amount/2 (for half)
     komis: Vec<i32>,
-    size: u8,
-    size_select_ref: NodeRef,
+    /// Width and height
+    size: (u8, u8),
     oncreate: Callback<()>,
     mods: GameModifier,
 }
 
 pub enum Msg {
     LoadPreset(Preset),
-    SelectSize(u8),
+    SetWidth(u8),
+    SetHeight(u8),
     SetName(String),
     TogglePixel,
     TogglePonnuki,
+    ToggleCaptureTarget,
     ToggleZen,
     ToggleHiddenMove,
     ToggleOneColor,
+    TogglePhantom,
     ToggleNoHistory,
     ToggleNPlusOne,
+    ToggleTerritoryScoring,
+    ToggleSuicide,
+    ToggleHandicap,
+    ToggleFreeHandicap,
+    SetHandicapStones(u32),
+    SelectKoRule(game::KoRule),
+    SelectTopology(game::Topology),
+    SelectGeometry(game::Geometry),
+    SelectTimeControl(Option<game::TimeControl>),
+    SelectUndoPolicy(game::UndoPolicy),
     SetHiddenMoveCount(u32),
     SetNPlusOneCount(u8),
     SetPonnukiValue(i32),
+    SetCaptureTarget(u32),
     OnCreate,
 }
 
@@ -64,8 +77,7 @@ impl Component for CreateGameView {
             user: props.user,
             seats: vec![],
             komis: vec![],
-            size: 19,
-            size_select_ref: NodeRef::default(),
+            size: (19, 19),
             oncreate: props.oncreate,
             mods: GameModifier::default(),
         };
@@ -85,16 +97,17 @@ impl Component for CreateGameView {
                 };
                 self.seats = seats;
                 self.komis = komi;
-                self.size = size;
+                self.size = (size, size);
                 // TODO: this is a hack
                 self.mods.zen_go = None;
-                if let Some(select) = self.size_select_ref.cast::<HtmlSelectElement>() {
-                    select.set_value(&size.to_string());
-                }
                 true
             }
-            Msg::SelectSize(size) => {
-                self.size = size;
+            Msg::SetWidth(width) => {
+                self.size.0 = width;
+                true
+            }
+            Msg::SetHeight(height) => {
+                self.size.1 = height;
                 true
             }
             Msg::SetName(name) => {
@@ -109,6 +122,59 @@ impl Component for CreateGameView {
                 self.mods.no_history = !self.mods.no_history;
                 true
             }
+            Msg::ToggleTerritoryScoring => {
+                self.mods.scoring = match self.mods.scoring {
+                    game::ScoringRules::Area => game::ScoringRules::Territory,
+                    game::ScoringRules::Territory => game::ScoringRules::Area,
+                };
+                true
+            }
+            Msg::ToggleHandicap => {
+                self.mods.handicap = match &self.mods.handicap {
+                    None => Some(game::Handicap {
+                        stones: 2,
+                        free_placement: false,
+                    }),
+                    Some(_) => None,
+                };
+                true
+            }
+            Msg::ToggleFreeHandicap => {
+                if let Some(handicap) = &mut self.mods.handicap {
+                    handicap.free_placement = !handicap.free_placement;
+                }
+                true
+            }
+            Msg::SetHandicapStones(stones) => {
+                if let Some(handicap) = &mut self.mods.handicap {
+                    handicap.stones = stones;
+                }
+                true
+            }
+            Msg::ToggleSuicide => {
+                self.mods.suicide = !self.mods.suicide;
+                true
+            }
+            Msg::SelectKoRule(rule) => {
+                self.mods.ko = rule;
+                true
+            }
+            Msg::SelectTopology(topology) => {
+                self.mods.topology = topology;
+                true
+            }
+            Msg::SelectGeometry(geometry) => {
+                self.mods.geometry = geometry;
+                true
+            }
+            Msg::SelectTimeControl(control) => {
+                self.mods.time_control = control;
+                true
+            }
+            Msg::SelectUndoPolicy(policy) => {
+                self.mods.undo = policy;
+                true
+            }
             Msg::TogglePonnuki => {
                 self.mods.ponnuki_is_points = match self.mods.ponnuki_is_points {
                     Some(_) => None,
@@ -117,6 +183,13 @@ impl Component for CreateGameView {
                 };
                 true
             }
+            Msg::ToggleCaptureTarget => {
+                self.mods.capture_target = match self.mods.capture_target {
+                    Some(_) => None,
+                    None => Some(game::CaptureTarget { stones: 1 }),
+                };
+                true
+            }
             Msg::ToggleZen => {
                 self.mods.zen_go = match &self.mods.zen_go {
                     None => {
@@ -176,6 +249,12 @@ impl Component for CreateGameView {
                 };
                 true
             }
+            Msg::SetCaptureTarget(stones) => {
+                if let Some(target) = &mut self.mods.capture_target {
+                    target.stones = stones;
+                }
+                true
+            }
             Msg::ToggleOneColor => {
                 self.mods.visibility_mode = match self.mods.visibility_mode {
                     Some(game::VisibilityMode::OneColor) => None,
@@ -183,6 +262,13 @@ impl Component for CreateGameView {
                 };
                 true
             }
+            Msg::TogglePhantom => {
+                self.mods.visibility_mode = match self.mods.visibility_mode {
+                    Some(game::VisibilityMode::Phantom) => None,
+                    _ => Some(game::VisibilityMode::Phantom),
+                };
+                true
+            }
             Msg::OnCreate => {
                 if self.seats.is_empty() || self.komis.is_empty() {
                     return false;
@@ -191,7 +277,7 @@ impl Component for CreateGameView {
                     name: self.name.clone(),
                     seats: self.seats.clone(),
                     komis: self.komis.clone(),
-                    size: (self.size, self.size),
+                    size: self.size,
                     mods: self.mods.clone(),
                 });
                 self.oncreate.emit(());
@@ -257,139 +343,162 @@ impl Component for CreateGameView {
             </ul>
         };
 
-        let select_size = self.link.callback(|event| match event {
+        let size_input = |value: u8, msg: fn(u8) -> Msg| {
+            html! {
+                <input
+                    style="width: 3em;"
+                    type="number"
+                    min=1
+                    max=game::MAX_BOARD_SIZE
+                    value=value
+                    onchange=self.link.callback(move |data| match data {
+                        ChangeData::Value(v) => msg(v.parse().unwrap_or(value)),
+                        _ => unreachable!(),
+                    }) />
+            }
+        };
+
+        let size_selection = html! {
+            <>
+                {size_input(self.size.0, Msg::SetWidth)}
+                {" x "}
+                {size_input(self.size.1, Msg::SetHeight)}
+            </>
+        };
+
+        let select_ko = self.link.callback(|event| match event {
+            ChangeData::Select(elem) => Msg::SelectKoRule(match elem.selected_index() {
+                0 => game::KoRule::Simple,
+                1 => game::KoRule::Positional,
+                2 => game::KoRule::Situational,
+                _ => unreachable!(),
+            }),
+            _ => unreachable!(),
+        });
+
+        let ko_selection = html! {
+            <select onchange=select_ko>
+                <option selected=self.mods.ko == game::KoRule::Simple>{ "Simple ko" }</option>
+                <option selected=self.mods.ko == game::KoRule::Positional>{ "Positional superko" }</option>
+                <option selected=self.mods.ko == game::KoRule::Situational>{ "Situational superko" }</option>
+            </select>
+        };
+
+        let select_topology = self.link.callback(|event| match event {
+            ChangeData::Select(elem) => Msg::SelectTopology(match elem.selected_index() {
+                0 => game::Topology::Plane,
+                1 => game::Topology::Cylinder,
+                2 => game::Topology::Torus,
+                _ => unreachable!(),
+            }),
+            _ => unreachable!(),
+        });
+
+        let topology_selection = html! {
+            <select onchange=select_topology>
+                <option selected=self.mods.topology == game::Topology::Plane>{ "Plane" }</option>
+                <option selected=self.mods.topology == game::Topology::Cylinder>{ "Cylinder" }</option>
+                <option selected=self.mods.topology == game::Topology::Torus>{ "Torus" }</option>
+            </select>
+        };
+
+        let select_geometry = self.link.callback(|event| match event {
+            ChangeData::Select(elem) => Msg::SelectGeometry(match elem.selected_index() {
+                0 => game::Geometry::Square,
+                1 => game::Geometry::Hex,
+                2 => game::Geometry::Cube,
+                _ => unreachable!(),
+            }),
+            _ => unreachable!(),
+        });
+
+        let geometry_selection = html! {
+            <select onchange=select_geometry>
+                <option selected=self.mods.geometry == game::Geometry::Square>{ "Square" }</option>
+                <option selected=self.mods.geometry == game::Geometry::Hex>{ "Hex" }</option>
+                <option selected=self.mods.geometry == game::Geometry::Cube>{ "Cube" }</option>
+            </select>
+        };
+
+        const MINUTE: game::Millis = 60_000;
+        let time_controls = [
+            ("No clock", None),
+            ("10 min", Some(game::TimeControl::Absolute { main: 10 * MINUTE })),
+            (
+                "5 min + 10 s",
+                Some(game::TimeControl::Fischer {
+                    main: 5 * MINUTE,
+                    increment: 10_000,
+                }),
+            ),
+            (
+                "10 min + 5x30 s byo-yomi",
+                Some(game::TimeControl::ByoYomi {
+                    main: 10 * MINUTE,
+                    periods: 5,
+                    period: 30_000,
+                }),
+            ),
+            (
+                "10 min + 5 min / 25 stones",
+                Some(game::TimeControl::Canadian {
+                    main: 10 * MINUTE,
+                    stones: 25,
+                    period: 5 * MINUTE,
+                }),
+            ),
+        ];
+
+        let controls = time_controls.clone();
+        let select_time = self.link.callback(move |event| match event {
             ChangeData::Select(elem) => {
-                let value = elem.selected_index();
-                Msg::SelectSize(match value {
-                    0 => 9,
-                    1 => 13,
-                    2 => 19,
-                    _ => unreachable!(),
-                })
+                Msg::SelectTimeControl(controls[elem.selected_index() as usize].1.clone())
             }
             _ => unreachable!(),
         });
 
-        let size_selection = html! {
-            <select
-                ref=self.size_select_ref.clone()
-                onchange=select_size
-            >
-                <option value=9 selected=self.size == 9>{ "9" }</option>
-                <option value=13 selected=self.size == 13>{ "13" }</option>
-                <option value=19 selected=self.size == 19>{ "19" }</option>
+        let time_selection = html! {
+            <select onchange=select_time>
+                {for time_controls.iter().map(|(name, control)| html! {
+                    <option selected=&self.mods.time_control == control>{ name }</option>
+                })}
+            </select>
+        };
+
+        let select_undo = self.link.callback(|event| match event {
+            ChangeData::Select(elem) => Msg::SelectUndoPolicy(match elem.selected_index() {
+                0 => game::UndoPolicy::Free,
+                1 => game::UndoPolicy::Approval,
+                2 => game::UndoPolicy::Never,
+                _ => unreachable!(),
+            }),
+            _ => unreachable!(),
+        });
+
+        let undo_selection = html! {
+            <select onchange=select_undo>
+                <option selected=self.mods.undo == game::UndoPolicy::Free>{ "Free" }</option>
+                <option selected=self.mods.undo == game::UndoPolicy::Approval>{ "With approval" }</option>
+                <option selected=self.mods.undo == game::UndoPolicy::Never>{ "Never" }</option>
             </select>
         };
 
         let oncreate = self.link.callback(|_| Msg::OnCreate);
+        let incompatible = game::check_compatible(&self.mods).err();
 
         let options = html! {
             <div style="padding: 1em; flex-grow: 1;">
                 <div>
                     {"Presets:"} {presets}
                     <span>{"Size: "} {size_selection}</span>
+                    <span>{" Ko: "} {ko_selection}</span>
+                    <span>{" Board: "} {topology_selection} {geometry_selection}</span>
+                    <span>{" Clock: "} {time_selection}</span>
+                    <span>{" Undo: "} {undo_selection}</span>
                 </div>
                 <div>
                     {"Modifiers"}
-                    <ul>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.hidden_move.is_some()
-                                onclick=self.link.callback(move |_| Msg::ToggleHiddenMove) />
-                            <label onclick=self.link.callback(move |_| Msg::ToggleHiddenMove)>{"Hidden move go"}</label>
-                            {" Placement stones: "}
-                            <input
-                                style="width: 3em;"
-                                type="number"
-                                value={self.mods.hidden_move.as_ref().map_or(5, |x| x.placement_count)}
-                                disabled=self.mods.hidden_move.is_none()
-                                onchange=self.link.callback(|data|
-                                    match data {
-                                        yew::events::ChangeData::Value(v) => Msg::SetHiddenMoveCount(v.parse().unwrap()),
-                                        _ => unreachable!(),
-                                    }
-                                ) />
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.pixel
-                                onclick=self.link.callback(move |_| Msg::TogglePixel) />
-                            <label onclick=self.link.callback(move |_| Msg::TogglePixel)>
-                                {"Pixel go"}
-                            </label>
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.zen_go.is_some()
-                                onclick=self.link.callback(move |_| Msg::ToggleZen) />
-                            <label onclick=self.link.callback(move |_| Msg::ToggleZen)>{"Zen go"}</label>
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=matches!(self.mods.visibility_mode, Some(game::VisibilityMode::OneColor))
-                                onclick=self.link.callback(move |_| Msg::ToggleOneColor) />
-                            <label onclick=self.link.callback(move |_| Msg::ToggleOneColor)>{"One color go"}</label>
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.no_history
-                                onclick=self.link.callback(move |_| Msg::ToggleNoHistory) />
-                            <label onclick=self.link.callback(move |_| Msg::ToggleNoHistory)>
-                                {"No history (good for one color)"}
-                            </label>
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.n_plus_one.is_some()
-                                onclick=self.link.callback(move |_| Msg::ToggleNPlusOne) />
-                            <label onclick=self.link.callback(move |_| Msg::ToggleNPlusOne)>
-                                {"N+1 "}
-                            </label>
-                            <input
-                                style="width: 3em;"
-                                type="number"
-                                value={self.mods.n_plus_one.as_ref().map_or(4, |x| x.length)}
-                                disabled=self.mods.n_plus_one.is_none()
-                                onchange=self.link.callback(|data|
-                                    match data {
-                                        yew::events::ChangeData::Value(v) => Msg::SetNPlusOneCount(v.parse().unwrap()),
-                                        _ => unreachable!(),
-                                    }
-                                ) />
-                        </li>
-                        <li>
-                            <input
-                                type="checkbox"
-                                class="toggle"
-                                checked=self.mods.ponnuki_is_points.is_some()
-                                onclick=self.link.callback(move |_| Msg::TogglePonnuki) />
-                            <label onclick=self.link.callback(move |_| Msg::TogglePonnuki)>{"Ponnuki is: "}</label>
-                            <input
-                                style="width: 3em;"
-                                type="number"
-                                value={self.mods.ponnuki_is_points.map_or(30, |x| x/2)}
-                                disabled=self.mods.ponnuki_is_points.is_none()
-                                onchange=self.link.callback(|data|
-                                    match data {
-                                        yew::events::ChangeData::Value(v) => Msg::SetPonnukiValue(v.parse().unwrap()),
-                                        _ => unreachable!(),
-                                    }
-                                ) />
-                            {" points (can be negative)"}
-                        </li>
-                    </ul>
+                    {self.view_modifiers()}
                 </div>
             </div>
         };
@@ -415,30 +524,191 @@ impl Component for CreateGameView {
                         </div>
                     </div>
                 </div>
-                <button onclick=oncreate>{"Create"}</button>
-                <div>
-                <p>
-                    {r#"Every game mode uses Tromp-Taylor rules, which are quite close to Chinese rules. Area counting, captures give no points.
-                    Superko, so ko is only in effect when the board repeats - there is no direct ko in three color go."#}
-                </p>
-                <p>
-                    {r#"Hidden move go: Each team places stones before the game starts.
-                    The opponents and viewers can't see their stones. Stones are revealed if they cause a capture or prevent a move from being made."#}
-                </p>
-                <p>
-                    {r#"Pixel go: You place 2x2 blobs. Overlapping stones are ignored."#}
-                </p>
-                <p>
-                    {r#"Zen go: One extra player. You get a different color on every turn. There are no winners."#}
-                </p>
-                <p>
-                    {r#"No history: No one can browse the past moves during the game."#}
-                </p>
-                <p>
-                    {r#"N+1: You get an extra turn when you make a row of exactly N stones horizontally, vertically or diagonally."#}
-                </p>
-                </div>
+                <button onclick=oncreate disabled=incompatible.is_some()>{"Create"}</button>
+                { for incompatible.map(|e| html! { <p>{ e.to_string() }</p> }) }
+                {Self::view_help()}
+            </div>
             </div>
+        }
+    }
+}
+
+impl CreateGameView {
+    /// Toggles for the game variants and rules, kept out of `view` so its `html!` stays
+    /// within the macro recursion limit.
+    fn view_modifiers(&self) -> Html {
+        let mods = &self.mods;
+        html! {
+            <ul>
+                <li>
+                    {self.view_toggle(mods.hidden_move.is_some(), || Msg::ToggleHiddenMove, "Hidden move go")}
+                    {" Placement stones: "}
+                    {self.view_number(
+                        mods.hidden_move.as_ref().map_or(5, |x| x.placement_count),
+                        mods.hidden_move.is_some(),
+                        |v| Msg::SetHiddenMoveCount(v.parse().unwrap()),
+                    )}
+                </li>
+                <li>{self.view_toggle(mods.pixel, || Msg::TogglePixel, "Pixel go")}</li>
+                <li>{self.view_toggle(mods.zen_go.is_some(), || Msg::ToggleZen, "Zen go")}</li>
+                <li>
+                    {self.view_toggle(
+                        matches!(mods.visibility_mode, Some(game::VisibilityMode::OneColor)),
+                        || Msg::ToggleOneColor,
+                        "One color go",
+                    )}
+                </li>
+                <li>
+                    {self.view_toggle(
+                        matches!(mods.visibility_mode, Some(game::VisibilityMode::Phantom)),
+                        || Msg::TogglePhantom,
+                        "Phantom go",
+                    )}
+                </li>
+                <li>
+                    {self.view_toggle(mods.no_history, || Msg::ToggleNoHistory, "No history (good for one color)")}
+                </li>
+                <li>
+                    {self.view_toggle(mods.n_plus_one.is_some(), || Msg::ToggleNPlusOne, "N+1 ")}
+                    {self.view_number(
+                        mods.n_plus_one.as_ref().map_or(4, |x| x.length),
+                        mods.n_plus_one.is_some(),
+                        |v| Msg::SetNPlusOneCount(v.parse().unwrap()),
+                    )}
+                </li>
+                <li>
+                    {self.view_toggle(mods.ponnuki_is_points.is_some(), || Msg::TogglePonnuki, "Ponnuki is: ")}
+                    {self.view_number(
+                        mods.ponnuki_is_points.map_or(30, |x| x / 2),
+                        mods.ponnuki_is_points.is_some(),
+                        |v| Msg::SetPonnukiValue(v.parse().unwrap()),
+                    )}
+                    {" points (can be negative)"}
+                </li>
+                <li>
+                    {self.view_toggle(mods.capture_target.is_some(), || Msg::ToggleCaptureTarget, "Capture ")}
+                    {self.view_number(
+                        mods.capture_target.as_ref().map_or(1, |x| x.stones),
+                        mods.capture_target.is_some(),
+                        |v| Msg::SetCaptureTarget(v.parse().unwrap()),
+                    )}
+                    {" stones to win"}
+                </li>
+                <li>
+                    {self.view_toggle(
+                        mods.scoring == game::ScoringRules::Territory,
+                        || Msg::ToggleTerritoryScoring,
+                        "Japanese scoring",
+                    )}
+                </li>
+                <li>{self.view_toggle(mods.suicide, || Msg::ToggleSuicide, "Suicide")}</li>
+                <li>
+                    {self.view_toggle(mods.handicap.is_some(), || Msg::ToggleHandicap, "Handicap: ")}
+                    {self.view_number(
+                        mods.handicap.as_ref().map_or(2, |x| x.stones),
+                        mods.handicap.is_some(),
+                        |v| Msg::SetHandicapStones(v.parse().unwrap()),
+                    )}
+                    {" stones "}
+                    <input
+                        type="checkbox"
+                        class="toggle"
+                        checked=mods.handicap.as_ref().is_some_and(|x| x.free_placement)
+                        disabled=mods.handicap.is_none()
+                        onclick=self.link.callback(move |_| Msg::ToggleFreeHandicap) />
+                    <label onclick=self.link.callback(move |_| Msg::ToggleFreeHandicap)>{"free placement"}</label>
+                </li>
+            </ul>
+        }
+    }
+
+    /// A checkbox with a label that can be clicked too.
+    fn view_toggle(&self, checked: bool, toggle: fn() -> Msg, label: &str) -> Html {
+        html! {
+            <>
+                <input
+                    type="checkbox"
+                    class="toggle"
+                    checked=checked
+                    onclick=self.link.callback(move |_| toggle()) />
+                <label onclick=self.link.callback(move |_| toggle())>{label}</label>
+            </>
+        }
+    }
+
+    /// A small number input for the setting of a variant, disabled while it is off.
+    fn view_number(&self, value: impl ToString, enabled: bool, set: fn(String) -> Msg) -> Html {
+        html! {
+            <input
+                style="width: 3em;"
+                type="number"
+                value=value.to_string()
+                disabled=!enabled
+                onchange=self.link.callback(move |data| match data {
+                    ChangeData::Value(v) => set(v),
+                    _ => unreachable!(),
+                }) />
+        }
+    }
+
+    /// What the options mean.
+    fn view_help() -> Html {
+        html! {
+            <div>
+            <p>
+                {r#"Every game mode uses Tromp-Taylor rules, which are quite close to Chinese rules. Area counting, captures give no points.
+                Positional superko by default, so ko is only in effect when the board repeats - there is no direct ko in three color go."#}
+            </p>
+            <p>
+                {r#"Simple ko: A ko can't be retaken right away, other repeating positions are allowed.
+                Situational superko: A position can only repeat when a different seat makes it."#}
+            </p>
+            <p>
+                {r#"Hidden move go: Each team places stones before the game starts.
+                The opponents and viewers can't see their stones. Stones are revealed if they cause a capture or prevent a move from being made."#}
+            </p>
+            <p>
+                {r#"Phantom go: You only see your own stones. Playing on a stone you can't see shows it to your team and you try again.
+                Captures show the stones involved to everyone, and the whole board is shown once the game ends."#}
+            </p>
+            <p>
+                {r#"Pixel go: You place 2x2 blobs. Overlapping stones are ignored."#}
+            </p>
+            <p>
+                {r#"Zen go: One extra player. You get a different color on every turn. There are no winners."#}
+            </p>
+            <p>
+                {r#"No history: No one can browse the past moves during the game."#}
+            </p>
+            <p>
+                {r#"N+1: You get an extra turn when you make a row of exactly N stones horizontally, vertically or diagonally."#}
+            </p>
+            <p>
+                {r#"Japanese scoring: Territory counting. You get a point for every empty point you surround and every stone you capture, including dead stones.
+                Empty points in seki are nobody's territory."#}
+            </p>
+            <p>
+                {r#"Handicap: Black starts with 2 to 9 stones on the star points, or placed anywhere with free placement. White moves first.
+                Komi becomes half a point, and white gets a point per handicap stone with area counting."#}
+            </p>
+            <p>
+                {r#"Undo: Free lets the player to move take back the last move right away. With approval every other player has to accept the request first."#}
+            </p>
+            <p>
+                {r#"Clock: The clock of the first player starts when the game is created, or when placing handicap stones ends. Running out of time ends the game.
+                Byo-yomi gives a number of periods once the main time is used, each move has to be made within one. Canadian needs a number of stones played within each period."#}
+            </p>
+            <p>
+                {r#"Board: A cylinder connects the left and right edges, a torus also connects the top and bottom edges.
+                Chains, liberties, territory, N+1 lines and pixel blobs continue across the connected edges.
+                Hex boards give every point six neighbours. A cube of side n is played on a 4n x 3n board, unfolded into a cross whose outer edges join up like the faces of the cube."#}
+            </p>
+            <p>
+                {r#"Capture: The first team to capture this many stones wins right away. With one stone this is atari go."#}
+            </p>
+            <p>
+                {r#"Suicide: You can fill the last liberty of your own group of two or more stones, which removes it from the board."#}
+            </p>
             </div>
         }
     }
@@ -33,6 +33,9 @@ pub struct GamePane {
 pub enum Msg {
     Pass,
     Cancel,
+    Resign,
+    RequestUndo,
+    AnswerUndo(bool),
     GetBoardAt(u32),
     ScanBoard(i32),
     ResetHistory,
@@ -51,6 +54,7 @@ pub struct Props {
 struct Callbacks {
     pass: Callback<()>,
     cancel: Callback<()>,
+    resign: Callback<()>,
 }
 
 impl Component for GamePane {
@@ -61,6 +65,7 @@ impl Component for GamePane {
         let callbacks = Callbacks {
             pass: link.callback(|_| Msg::Pass),
             cancel: link.callback(|_| Msg::Cancel),
+            resign: link.callback(|_| Msg::Resign),
         };
 
         // Currently the state is passed back through props so we don't care about the output
@@ -104,6 +109,10 @@ impl Component for GamePane {
         match msg {
             Msg::Pass => networking::send(GameAction::Pass),
             Msg::Cancel => networking::send(GameAction::Cancel),
+            Msg::Resign => networking::send(GameAction::Resign),
+            Msg::RequestUndo => networking::send(GameAction::RequestUndo),
+            Msg::AnswerUndo(true) => networking::send(GameAction::AcceptUndo),
+            Msg::AnswerUndo(false) => networking::send(GameAction::DeclineUndo),
             Msg::GetBoardAt(turn) => {
                 self.game_store.get_board_at(turn);
             }
@@ -136,12 +145,17 @@ impl Component for GamePane {
             game,
             profiles,
         } = &self.props;
-        let Callbacks { pass, cancel } = &self.callbacks;
+        let Callbacks {
+            pass,
+            cancel,
+            resign,
+        } = &self.callbacks;
 
         // FIXME: Reforming the callbacks prevents yew from optimizing for equality.
         // Either patch it upstream or make the callbacks have the proper shape.
         let pass = pass.reform(|_| ());
         let cancel = cancel.reform(|_| ());
+        let resign = resign.reform(|_| ());
 
         let userlist = game
             .members
@@ -163,14 +177,20 @@ impl Component for GamePane {
             })
             .collect::<Html>();
 
-        let status = match game.state {
-            game::GameState::FreePlacement(_) => "Free placement",
-            game::GameState::Play(_) => "Active",
-            game::GameState::Scoring(_) => "Scoring",
-            game::GameState::Done(_) => "Game over!",
+        let status = match &game.state {
+            game::GameStateView::FreePlacement(state) if state.handicap => {
+                "Handicap placement".to_owned()
+            }
+            game::GameStateView::FreePlacement(_) => "Free placement".to_owned(),
+            game::GameStateView::Play(_) => "Active".to_owned(),
+            game::GameStateView::Scoring(_) => "Scoring".to_owned(),
+            game::GameStateView::Done(_) => match &game.result {
+                Some(result) => format!("Game over! {}", result_text(result)),
+                None => "Game over!".to_owned(),
+            },
         };
 
-        let game_done = matches!(game.state, game::GameState::Done(_));
+        let game_done = matches!(game.state, game::GameStateView::Done(_));
 
         let hidden_stones_left = if game.hidden_stones_left > 0 {
             html!(<>{"Opponents' hidden stones left: "}{game.hidden_stones_left}</>)
@@ -178,20 +198,61 @@ impl Component for GamePane {
             html!()
         };
 
-        let pass_button = match game.state {
-            game::GameState::FreePlacement(_) => html!(<button onclick=pass>{"Ready"}</button>),
-            game::GameState::Play(_) => html!(<button onclick=pass>{"Pass"}</button>),
-            game::GameState::Scoring(_) => html!(<button onclick=pass>{"Accept"}</button>),
-            game::GameState::Done(_) => html!(),
+        let pass_button = match &game.state {
+            game::GameStateView::FreePlacement(state) if state.handicap => html!(),
+            game::GameStateView::FreePlacement(_) => html!(<button onclick=pass>{"Ready"}</button>),
+            game::GameStateView::Play(_) => html!(<button onclick=pass>{"Pass"}</button>),
+            game::GameStateView::Scoring(_) => html!(<button onclick=pass>{"Accept"}</button>),
+            game::GameStateView::Done(_) => html!(),
         };
 
         let cancel_button = match game.state {
-            game::GameState::FreePlacement(_) => html!(<button onclick=cancel>{"Clear"}</button>),
-            game::GameState::Play(_) => html!(<button onclick=cancel>{"Undo"}</button>),
-            game::GameState::Scoring(_) => html!(<button onclick=cancel>{"Cancel"}</button>),
+            game::GameStateView::FreePlacement(_) => html!(<button onclick=cancel>{"Clear"}</button>),
+            game::GameStateView::Play(_) => match game.mods.undo {
+                game::UndoPolicy::Free => html!(<button onclick=cancel>{"Undo"}</button>),
+                game::UndoPolicy::Approval => html!(
+                    <button onclick=self.link.callback(|_| Msg::RequestUndo)>{"Request undo"}</button>
+                ),
+                game::UndoPolicy::Never => html!(),
+            },
+            game::GameStateView::Scoring(_) => html!(<button onclick=cancel>{"Cancel"}</button>),
             _ => html!(),
         };
 
+        let undo_request = match &game.state {
+            game::GameStateView::Play(state) => state.undo_request.as_ref(),
+            _ => None,
+        };
+        let undo_answer = match undo_request {
+            Some(request) => {
+                let answering = game.seats.iter().zip(&request.accepted).any(|((player, _), accepted)| {
+                    !accepted && user.as_ref().map(|x| x.user_id) == *player
+                });
+                let buttons = if answering {
+                    html! {
+                        <>
+                        <button onclick=self.link.callback(|_| Msg::AnswerUndo(true))>{"Accept"}</button>
+                        <button onclick=self.link.callback(|_| Msg::AnswerUndo(false))>{"Decline"}</button>
+                        </>
+                    }
+                } else {
+                    html!()
+                };
+                html!(<div>{"Undo requested "}{buttons}</div>)
+            }
+            None => html!(),
+        };
+
+        let seated = game
+            .seats
+            .iter()
+            .any(|(player, _)| user.as_ref().map(|x| x.user_id) == *player);
+        let resign_button = if seated && !game_done {
+            html!(<button onclick=resign>{"Resign"}</button>)
+        } else {
+            html!()
+        };
+
         let game_length = game.move_number;
         let view_turn = match &game.history {
             Some(h) => h.move_number,
@@ -259,7 +320,8 @@ impl Component for GamePane {
                  style="flex-grow: 1; margin: 10px; display: flex; justify-content: center;">
                 <div style=game_wrapper_style>
                     <div style=game_container_style>
-                        <div>{"Status:"} {status} {pass_button} {cancel_button} {hidden_stones_left}</div>
+                        <div>{"Status:"} {status} {pass_button} {cancel_button} {resign_button} {hidden_stones_left}</div>
+                        {undo_answer}
                         <board::Board game=game size=self.size/>
                         {turn_bar}
                     </div>
@@ -297,3 +359,22 @@ fn size_from_dimensions(pane: &NodeRef, window: WindowDimensions) -> i32 {
 
     size
 }
+
+fn result_text(result: &game::GameResult) -> String {
+    let winner = match result.winner {
+        Some(winner) => winner,
+        None if result.reason == game::ResultReason::Score => return "Tie".to_owned(),
+        None => return "No winner".to_owned(),
+    };
+    let how = match result.reason {
+        game::ResultReason::Score => {
+            let margin = result.margin.unwrap_or(0);
+            format!("by {:.1} points", margin as f32 / 2.)
+        }
+        game::ResultReason::Resignation => "by resignation".to_owned(),
+        game::ResultReason::Timeout => "on time".to_owned(),
+        game::ResultReason::Forfeit => "by forfeit".to_owned(),
+        game::ResultReason::Captures => "by captures".to_owned(),
+    };
+    format!("{} wins {}", game::Color::name(winner), how)
+}
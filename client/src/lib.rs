@@ -129,6 +129,8 @@ impl Component for GameApp {
                     mods,
                     points,
                     move_number,
+                    clock,
+                    result,
                 }) => {
                     game.emit(GameView {
                         room_id,
@@ -143,6 +145,8 @@ impl Component for GameApp {
                         mods,
                         points,
                         move_number,
+                        clock,
+                        result,
                         history: None,
                     });
                 }
@@ -378,6 +382,7 @@ impl Component for GameApp {
                 message::Error::GameStartTimer(x) => {
                     format!("You can only create a game every 2 minutes ({}s left)", x)
                 }
+                message::Error::Rules(x) => format!("Rules not accepted: {}", x),
                 message::Error::Other(x) => x.to_string(),
             };
             html! {
@@ -1,15 +1,20 @@
 use std::collections::HashMap;
+use std::time::Duration;
 use yew::prelude::*;
+use yew::services::interval::{IntervalService, IntervalTask};
 
-use crate::game::GameState;
+use crate::game::GameStateView;
 use crate::game_view::*;
 use crate::message::{self, ClientMessage};
 use crate::networking;
-use shared::game::Color;
+use shared::game::{Color, Millis};
 
 pub struct SeatList {
     link: ComponentLink<Self>,
     props: Props,
+    /// When the clock in props arrived, to count the running one down from
+    clock_received: f64,
+    _clock_tick: IntervalTask,
 }
 
 #[derive(Properties, Clone, PartialEq)]
@@ -22,6 +27,7 @@ pub struct Props {
 pub enum Msg {
     TakeSeat(u32),
     LeaveSeat(u32),
+    ClockTick,
 }
 
 impl Component for SeatList {
@@ -29,7 +35,14 @@ impl Component for SeatList {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        SeatList { link, props }
+        let _clock_tick =
+            IntervalService::spawn(Duration::from_secs(1), link.callback(|_| Msg::ClockTick));
+        SeatList {
+            link,
+            props,
+            clock_received: js_sys::Date::now(),
+            _clock_tick,
+        }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
@@ -40,12 +53,16 @@ impl Component for SeatList {
             Msg::LeaveSeat(idx) => networking::send(ClientMessage::GameAction(
                 message::GameAction::LeaveSeat(idx),
             )),
+            Msg::ClockTick => return self.props.game.clock.is_some(),
         }
         true
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         if self.props != props {
+            if self.props.game.clock != props.game.clock {
+                self.clock_received = js_sys::Date::now();
+            }
             self.props = props;
             true
         } else {
@@ -56,7 +73,7 @@ impl Component for SeatList {
     fn view(&self) -> Html {
         let game = &self.props.game;
         let scores = match &self.props.game.state {
-            GameState::Scoring(state) | GameState::Done(state) => Some(&state.scores[..]),
+            GameStateView::Scoring(state) | GameStateView::Done(state) => Some(&state.scores[..]),
             _ => Some(&self.props.game.points[..]),
         };
 
@@ -97,15 +114,15 @@ impl Component for SeatList {
                     };
 
                     let passed = match &game.state {
-                        GameState::FreePlacement(state) if state.players_ready[idx] => " - ready!",
-                        GameState::Play(state) if state.players_passed[idx] => " - passed!",
-                        GameState::Scoring(state) if state.players_accepted[idx] => " - accepted!",
+                        GameStateView::FreePlacement(state) if state.players_ready[idx] => " - ready!",
+                        GameStateView::Play(state) if state.players_passed[idx] => " - passed!",
+                        GameStateView::Scoring(state) if state.players_accepted[idx] => " - accepted!",
                         _ => "",
                     };
 
                     html! {
                         <div class=class style="margin: 5px 0;">
-                            {format!("{}: {} {}{}", colorname, nick, scoretext, passed)}
+                            {format!("{}: {} {}{}{}", colorname, nick, scoretext, passed, self.clock_text(idx))}
                             {leave}
                         </div>
                     }
@@ -125,3 +142,29 @@ impl Component for SeatList {
         html!(<div style="margin: 10px;">{list}</div>)
     }
 }
+
+impl SeatList {
+    fn clock_text(&self, seat: usize) -> String {
+        let clock = match &self.props.game.clock {
+            Some(clock) => clock,
+            None => return String::new(),
+        };
+        let mut remaining = clock.remaining[seat];
+        if clock.running == Some(seat) {
+            let elapsed = (js_sys::Date::now() - self.clock_received).max(0.) as Millis;
+            remaining = remaining.saturating_sub(elapsed);
+        }
+        let state = &clock.seats[seat];
+        let periods = if state.timed_out {
+            " (timed out)".to_owned()
+        } else if state.main == 0 && state.periods > 0 {
+            format!(" ({} periods)", state.periods)
+        } else if state.main == 0 && state.stones > 0 {
+            format!(" ({} stones)", state.stones)
+        } else {
+            String::new()
+        };
+        let seconds = remaining / 1000;
+        format!(" - {}:{:02}{}", seconds / 60, seconds % 60, periods)
+    }
+}
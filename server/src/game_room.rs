@@ -1,9 +1,10 @@
 use actix::prelude::*;
 use std::collections::{HashMap, HashSet};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::{db, server};
 use shared::game;
+use shared::game::TimeSource;
 use shared::message;
 
 // TODO: add room timeout
@@ -69,6 +70,22 @@ pub struct GameRoom {
     pub game: game::Game,
     pub db: Addr<db::DbActor>,
     pub server: Addr<server::GameServer>,
+    /// Ends the game when the running clock runs out
+    pub clock_timer: Option<SpawnHandle>,
+    /// Where the game clock gets the time from
+    pub time_source: Box<dyn TimeSource>,
+}
+
+/// Game clocks run on milliseconds since the unix epoch.
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> game::Millis {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as game::Millis)
+            .unwrap_or(0)
+    }
 }
 
 impl GameRoom {
@@ -77,11 +94,69 @@ impl GameRoom {
             let _ = addr.do_send(create_msg(*user_id));
         }
     }
+
+    fn send_status(&self) {
+        self.send_room_messages(|user_id| Message::GameStatus {
+            room_id: self.room_id,
+            members: self.users.iter().copied().collect(),
+            view: self.view(user_id),
+        });
+    }
+
+    fn view(&self, user_id: u64) -> game::GameView {
+        let mut view = self.game.get_view(user_id);
+        // Count the running clock down to now, not to the last action.
+        view.clock = self.game.clock_view(self.time_source.now());
+        view
+    }
+
+    fn store_game(&self) {
+        self.db.do_send(db::StoreGame {
+            id: Some(self.room_id as _),
+            name: self.name.clone(),
+            replay: Some(self.game.dump()),
+        });
+    }
+
+    /// Sets a timer for when the running clock runs out.
+    fn schedule_timeout(&mut self, ctx: &mut Context<Self>) {
+        if let Some(handle) = self.clock_timer.take() {
+            ctx.cancel_future(handle);
+        }
+
+        let now = self.time_source.now();
+        let remaining = match self.game.clock_view(now) {
+            Some(game::ClockView {
+                running: Some(seat),
+                remaining,
+                ..
+            }) => remaining[seat],
+            _ => return,
+        };
+        let handle = ctx.run_later(Duration::from_millis(remaining), |act, ctx| {
+            act.clock_timer = None;
+            if act.game.check_time(act.time_source.now()) {
+                act.store_game();
+                act.send_status();
+            } else {
+                act.schedule_timeout(ctx);
+            }
+        });
+        self.clock_timer = Some(handle);
+    }
 }
 
 impl Actor for GameRoom {
     type Context = Context<Self>;
 
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // New games start the clock here, loaded ones may have it running already.
+        if self.game.start_clock(self.time_source.now()) {
+            self.store_game();
+        }
+        self.schedule_timeout(ctx);
+    }
+
     fn stopping(&mut self, _ctx: &mut Self::Context) -> Running {
         println!("Room {} stopping!", self.room_id);
 
@@ -99,11 +174,7 @@ impl Handler<Leave> for GameRoom {
             let sessions = &self.sessions;
             if !sessions.values().any(|(uid, _addr)| *uid == user_id) {
                 self.users.remove(&user_id);
-                self.send_room_messages(|user_id| Message::GameStatus {
-                    room_id: self.room_id,
-                    members: self.users.iter().copied().collect(),
-                    view: self.game.get_view(user_id),
-                });
+                self.send_status();
             }
         }
     }
@@ -121,11 +192,7 @@ impl Handler<Join> for GameRoom {
 
         self.sessions.insert(session_id, (user_id, addr));
         self.users.insert(user_id);
-        self.send_room_messages(|user_id| Message::GameStatus {
-            room_id: self.room_id,
-            members: self.users.iter().copied().collect(),
-            view: self.game.get_view(user_id),
-        });
+        self.send_status();
 
         // TODO: Announce profile to room members
 
@@ -142,7 +209,7 @@ impl Handler<Join> for GameRoom {
 impl Handler<GameAction> for GameRoom {
     type Result = ();
 
-    fn handle(&mut self, msg: GameAction, _: &mut Context<Self>) {
+    fn handle(&mut self, msg: GameAction, ctx: &mut Context<Self>) {
         let GameAction { id, action } = msg;
 
         let &(user_id, ref addr) = match self.sessions.get(&id) {
@@ -151,24 +218,67 @@ impl Handler<GameAction> for GameRoom {
         };
 
         self.last_action = Instant::now();
+        let now = self.time_source.now();
+        if self.game.check_time(now) {
+            // Too late, the timer just hasn't fired yet.
+            self.store_game();
+            self.send_status();
+            return;
+        }
         // TODO: Handle errors in game actions - currently they fail quietly
         match action {
             message::GameAction::Place(x, y) => {
                 let res = self
                     .game
-                    .make_action(user_id, game::ActionKind::Place(x, y));
+                    .make_action_at(user_id, game::ActionKind::Place(x, y), now);
                 if res.is_err() {
                     return;
                 }
             }
             message::GameAction::Pass => {
-                let res = self.game.make_action(user_id, game::ActionKind::Pass);
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::Pass, now);
                 if res.is_err() {
                     return;
                 }
             }
             message::GameAction::Cancel => {
-                let res = self.game.make_action(user_id, game::ActionKind::Cancel);
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::Cancel, now);
+                if res.is_err() {
+                    return;
+                }
+            }
+            message::GameAction::Resign => {
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::Resign, now);
+                if res.is_err() {
+                    return;
+                }
+            }
+            message::GameAction::RequestUndo => {
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::RequestUndo, now);
+                if res.is_err() {
+                    return;
+                }
+            }
+            message::GameAction::AcceptUndo => {
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::AcceptUndo, now);
+                if res.is_err() {
+                    return;
+                }
+            }
+            message::GameAction::DeclineUndo => {
+                let res = self
+                    .game
+                    .make_action_at(user_id, game::ActionKind::DeclineUndo, now);
                 if res.is_err() {
                     return;
                 }
@@ -206,16 +316,8 @@ impl Handler<GameAction> for GameRoom {
             }
         }
 
-        self.db.do_send(db::StoreGame {
-            id: Some(self.room_id as _),
-            name: self.name.clone(),
-            replay: Some(self.game.dump()),
-        });
-
-        self.send_room_messages(|user_id| Message::GameStatus {
-            room_id: self.room_id,
-            members: self.users.iter().copied().collect(),
-            view: self.game.get_view(user_id),
-        });
+        self.store_game();
+        self.schedule_timeout(ctx);
+        self.send_status();
     }
 }
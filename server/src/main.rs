@@ -99,29 +99,7 @@ impl Handler<game_room::Message> for ClientWebSocket {
                 members,
                 view,
             } => {
-                ctx.binary(
-                    ServerMessage::GameStatus {
-                        room_id,
-                        members,
-                        seats: view
-                            .seats
-                            .into_iter()
-                            .map(|x| (x.player, x.team.0))
-                            .collect(),
-                        turn: view.turn,
-                        board: view.board.into_iter().map(|x| x.0).collect(),
-                        board_visibility: view
-                            .board_visibility
-                            .map(|b| b.iter().map(|x| x.into_value()).collect()),
-                        hidden_stones_left: view.hidden_stones_left,
-                        size: view.size,
-                        state: view.state,
-                        mods: view.mods,
-                        points: view.points.to_vec(),
-                        move_number: view.move_number,
-                    }
-                    .pack(),
-                );
+                ctx.binary(ServerMessage::game_status(room_id, members, view).pack());
             }
             game_room::Message::BoardAt { view, .. } => {
                 ctx.binary(ServerMessage::BoardAt(view).pack());
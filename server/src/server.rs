@@ -253,6 +253,8 @@ impl GameServer {
                             game,
                             db: act.db.clone(),
                             server: ctx.address(),
+                            clock_timer: None,
+                            time_source: Box::new(game_room::SystemClock),
                         };
 
                         let addr = room.start();
@@ -446,8 +448,8 @@ impl Handler<CreateRoom> for GameServer {
 
         let komis = komis.as_slice().into();
         let game = match game::Game::standard(&seats, komis, size, mods) {
-            Some(g) => g,
-            None => return ActorResponse::reply(Err(Error::other("Rules not accepted"))),
+            Ok(g) => g,
+            Err(e) => return ActorResponse::reply(Err(Error::Rules(e))),
         };
 
         profile.last_game_time = Some(Instant::now());
@@ -483,6 +485,8 @@ impl Handler<CreateRoom> for GameServer {
                     game,
                     db: act.db.clone(),
                     server: ctx.address(),
+                    clock_timer: None,
+                    time_source: Box::new(game_room::SystemClock),
                 };
 
                 let addr = room.start();
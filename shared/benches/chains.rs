@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use shared::game::{find_groups, ActionKind, Board, Chains, Game, GameModifier};
+
+/// Plays a long 19x19 game of pseudo random moves, skipping the illegal ones.
+fn long_game() -> Game {
+    let mut game = Game::standard(
+        &[1, 2],
+        tinyvec::tiny_vec![0, 15],
+        (19, 19),
+        GameModifier::default(),
+    )
+    .unwrap();
+    game.take_seat(1, 0).unwrap();
+    game.take_seat(2, 1).unwrap();
+
+    let mut seed = 0x2545_f491u32;
+    let mut moves = 0;
+    for _ in 0..10_000 {
+        if moves == 300 {
+            break;
+        }
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let idx = (seed >> 8) % 361;
+        let player = game.shared.turn as u64 + 1;
+        if game
+            .make_action(player, ActionKind::Place(idx % 19, idx / 19))
+            .is_ok()
+        {
+            moves += 1;
+        }
+    }
+    game
+}
+
+/// What capturing cost per move before: scanning the whole board twice.
+fn scan_every_move(boards: &[Board]) -> usize {
+    boards
+        .iter()
+        .map(|board| find_groups(board).len() + find_groups(board).len())
+        .sum()
+}
+
+/// The same moves played into `Chains`, with captures found the way `PlayState` does.
+fn update_every_move(boards: &[Board]) -> usize {
+    let mut chains = Chains::new(&boards[0]);
+    let mut total = 0;
+    for (before, after) in boards.iter().zip(&boards[1..]) {
+        for (idx, (&old, &new)) in before.points.iter().zip(&after.points).enumerate() {
+            if old.is_empty() && !new.is_empty() {
+                chains.add_stone(after.idx_to_coord(idx).unwrap(), new);
+            }
+        }
+        let dead = chains
+            .iter()
+            .filter(|c| c.liberties.is_empty())
+            .map(|c| c.stones[0])
+            .collect::<Vec<_>>();
+        for stone in dead {
+            chains.remove_chain(stone);
+        }
+        total += chains.iter().count();
+    }
+    total
+}
+
+fn benchmark(c: &mut Criterion) {
+    let game = long_game();
+    let dump = game.dump();
+    let boards = (0..game.shared.board_history.len())
+        .map(|turn| game.shared.position_at(turn).unwrap().board)
+        .collect::<Vec<_>>();
+
+    c.bench_function("replay 300 moves", |b| {
+        b.iter(|| Game::load(black_box(&dump)).unwrap())
+    });
+
+    let mut group = c.benchmark_group("groups per move");
+    group.bench_function("find_groups", |b| {
+        b.iter(|| scan_every_move(black_box(&boards)))
+    });
+    group.bench_function("chains", |b| {
+        b.iter(|| update_every_move(black_box(&boards)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, benchmark);
+criterion_main!(benches);
@@ -1,14 +1,34 @@
 mod board;
+mod chains;
+mod clock;
+mod compat;
+mod legal;
+mod replay;
+mod result;
+mod sgf;
+mod state_view;
+pub mod zobrist;
 
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::{HashSet, VecDeque};
+use std::fmt;
 
 use bitmaps::Bitmap;
 use tinyvec::TinyVec;
 
+use crate::modifiers::{BoardView, GameSetup, Modifiers};
 pub use crate::states::GameState;
-use crate::states::PlayState;
-pub use board::{Board, Point};
+use crate::states::{PlayState, ScoringState};
+pub use board::{Board, Geometry, Graph, Links, Point, Topology};
+pub use chains::{Chain, Chains};
+pub use clock::{ClockView, GameClock, Millis, SeatClock, TimeControl, TimeSource};
+pub use compat::{check_compatible, Variant};
+use replay::GameReplay;
+pub use replay::FORMAT_VERSION;
+pub use result::{GameResult, ResultReason, TeamResult};
+pub use sgf::SgfError;
+pub use state_view::{FreePlacementView, GameStateView};
 
 ///////////////////////////////////////////////////////////////////////////////
 //                                    Data                                   //
@@ -21,7 +41,7 @@ pub type VisibilityBoard = Board<Bitmap<typenum::U16>>;
 
 // Color //////////////////////////////////////////////////////////////////////
 
-#[derive(Debug, Copy, Clone, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct Color(pub u8);
 
@@ -86,6 +106,8 @@ pub struct Group {
     pub liberties: i32,
     pub team: Color,
     pub alive: bool,
+    /// Set during scoring for living groups in seki
+    pub seki: bool,
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -97,6 +119,19 @@ pub enum ActionKind {
     Place(u32, u32),
     Pass,
     Cancel,
+    Resign,
+    /// Asks the other seats to take back the last move.
+    RequestUndo,
+    AcceptUndo,
+    DeclineUndo,
+}
+
+/// Stones placed before the first move, e.g. an imported position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setup {
+    pub stones: Vec<(Point, Color)>,
+    /// The seat that moves first.
+    pub turn: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -104,21 +139,35 @@ pub enum ReplayActionKind {
     Play(ActionKind),
     TakeSeat(u32),
     LeaveSeat(u32),
+    Setup(Setup),
+    /// The running clock ran out, ending the game.
+    Timeout,
+    /// A team lost without playing on, e.g. by abandoning the game.
+    Forfeit(u8),
+    /// The clock of the seat to move started, before anyone moved.
+    StartClock,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GameAction {
     pub user_id: u64,
     pub action: ReplayActionKind,
+    /// When the action was made, on the game clock
+    #[serde(default)]
+    pub time: Millis,
 }
 
 impl GameAction {
-    fn new(user_id: u64, action: ReplayActionKind) -> Self {
-        GameAction { user_id, action }
+    fn new(user_id: u64, action: ReplayActionKind, time: Millis) -> Self {
+        GameAction {
+            user_id,
+            action,
+            time,
+        }
     }
 
-    fn play(user_id: u64, action: ActionKind) -> Self {
-        GameAction::new(user_id, ReplayActionKind::Play(action))
+    fn play(user_id: u64, action: ActionKind, time: Millis) -> Self {
+        GameAction::new(user_id, ReplayActionKind::Play(action), time)
     }
 }
 
@@ -137,12 +186,21 @@ pub struct HiddenMoveGo {
     pub teams_share_stones: bool,
 }
 
-/// Visibility modes describe how the game state should be displayed, without
-/// affecting the actual gameplay in any way.
+/// Visibility modes describe which stones each player sees.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum VisibilityMode {
     /// Display all stones as the same color for both players.
     OneColor,
+    /// Phantom go: every team only sees its own stones. Playing on a stone you can't see
+    /// shows it to your team, and you try again. Captures show the stones involved to
+    /// everyone.
+    Phantom,
+}
+
+/// Atari go and its relatives: the first team to capture this many stones wins right away.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureTarget {
+    pub stones: u32,
 }
 
 /// Based on the 4+1 variant where a player gets an extra turn if they make
@@ -152,6 +210,49 @@ pub struct NPlusOne {
     pub length: u8,
 }
 
+/// How the final score is counted.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum ScoringRules {
+    /// Chinese style: stones on the board and surrounded points count.
+    #[default]
+    Area,
+    /// Japanese style: surrounded points and prisoners count. Eyes in seki don't.
+    Territory,
+}
+
+/// Black starts with extra stones in a two color game. White moves first and komi becomes
+/// half a point, plus a point per stone with area scoring.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Handicap {
+    pub stones: u32,
+    /// Black places the stones anywhere before the game, instead of on the star points.
+    pub free_placement: bool,
+}
+
+/// When moves can be taken back during play.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum UndoPolicy {
+    Never,
+    /// Every other seat has to accept an undo request.
+    Approval,
+    /// The seat to move can take back the last move right away, for casual games.
+    #[default]
+    Free,
+}
+
+/// Which repeated positions are forbidden.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum KoRule {
+    /// Only the position from before the previous move can't be repeated, so a ko can't be
+    /// retaken right away.
+    Simple,
+    /// No earlier position may be repeated.
+    #[default]
+    Positional,
+    /// No earlier position may be repeated by the same seat.
+    Situational,
+}
+
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct GameModifier {
     /// Pixel go is a game mode where you place 2x2 blobs instead of a single stone.
@@ -179,35 +280,151 @@ pub struct GameModifier {
 
     #[serde(default)]
     pub n_plus_one: Option<NPlusOne>,
+
+    #[serde(default)]
+    pub scoring: ScoringRules,
+
+    #[serde(default)]
+    pub ko: KoRule,
+
+    /// New Zealand and Tromp-Taylor rules: a move may kill its own chain of more than one
+    /// stone, which is then removed. Single stone suicide stays illegal.
+    #[serde(default)]
+    pub suicide: bool,
+
+    #[serde(default)]
+    pub handicap: Option<Handicap>,
+
+    /// Every seat has a clock, running out of time loses the game.
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+
+    #[serde(default)]
+    pub undo: UndoPolicy,
+
+    /// Boards whose edges wrap around, see `Topology`.
+    #[serde(default)]
+    pub topology: Topology,
+
+    /// Hex, cube and custom graph boards, see `Geometry`.
+    #[serde(default)]
+    pub geometry: Geometry,
+
+    #[serde(default)]
+    pub capture_target: Option<CaptureTarget>,
+}
+
+/// Which revision of each rule a game is played with. Stored in replays so old games
+/// replay with the rules they were played under. Bump a revision whenever a change would
+/// make an old game end up different. Rules added later only get a field once they change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RuleVersions {
+    /// 1: dead stones are estimated when scoring starts, before everything was alive.
+    #[serde(default)]
+    pub scoring: u32,
+    /// 1: superko also compares which stones are hidden from whom.
+    #[serde(default)]
+    pub hidden_move: u32,
+    #[serde(default)]
+    pub pixel: u32,
+    #[serde(default)]
+    pub ponnuki: u32,
+    #[serde(default)]
+    pub zen_go: u32,
+    #[serde(default)]
+    pub visibility_mode: u32,
+    #[serde(default)]
+    pub no_history: u32,
+    #[serde(default)]
+    pub n_plus_one: u32,
+}
+
+impl RuleVersions {
+    /// The rules of replays from before they were versioned.
+    pub const ORIGINAL: RuleVersions = RuleVersions {
+        scoring: 0,
+        hidden_move: 0,
+        pixel: 0,
+        ponnuki: 0,
+        zen_go: 0,
+        visibility_mode: 0,
+        no_history: 0,
+        n_plus_one: 0,
+    };
+
+    /// The rules new games are played with.
+    pub const CURRENT: RuleVersions = RuleVersions {
+        scoring: 1,
+        hidden_move: 1,
+        ..RuleVersions::ORIGINAL
+    };
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 //                                   State                                   //
 ///////////////////////////////////////////////////////////////////////////////
 
+/// Largest width and height of a board.
+pub const MAX_BOARD_SIZE: u8 = 37;
+
+/// Every this many moves the history stores the whole position instead of the changes.
+const KEYFRAME_INTERVAL: usize = 32;
+
 #[derive(Debug, Clone, PartialEq)]
-pub struct BoardHistory {
-    pub hash: u64,
+pub struct Position {
     pub board: Board,
     pub board_visibility: Option<VisibilityBoard>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardHistory {
+    pub hash: u64,
+    pub visibility_hash: u64,
+    /// The whole position, stored every `KEYFRAME_INTERVAL` moves
+    pub keyframe: Option<Box<Position>>,
+    /// Points changed since the previous entry, with their new color
+    pub changes: Vec<(Point, Color)>,
+    /// Visibility changed since the previous entry
+    pub visibility_changes: Vec<(Point, Visibility)>,
     pub state: GameState,
     pub points: GroupVec<i32>,
+    pub captures: GroupVec<i32>,
     pub turn: usize,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct SharedState {
     pub seats: GroupVec<Seat>,
+    /// Seat colors the game was created with, zen go changes the teams as it goes
+    pub seat_colors: GroupVec<u8>,
     pub points: GroupVec<i32>,
+    /// Stones captured by each team, for territory scoring
+    pub captures: GroupVec<i32>,
     pub turn: usize,
     pub pass_count: usize,
     pub board: Board,
     pub board_visibility: Option<VisibilityBoard>,
+    /// Zobrist hash of `board`, updated with every change
+    pub hash: u64,
+    /// Zobrist hash of `board_visibility`, updated with every change
+    pub visibility_hash: u64,
+    /// Chains of `board`, updated with every change
+    pub chains: Chains,
     pub board_history: Vec<BoardHistory>,
+    /// Position of the last history entry, the next entry only stores changes to it
+    pub history_tip: Position,
     /// Optimization for superko
     pub capture_count: usize,
     pub komis: GroupVec<i32>,
     pub mods: GameModifier,
+    /// The variants of `mods`, for calling their hooks
+    pub modifiers: Modifiers,
+    pub rules: RuleVersions,
+    pub clock: Option<GameClock>,
+    /// Time of the last action. Actions made without a time are made at it.
+    pub time: Millis,
+    /// Set once the game is done
+    pub result: Option<GameResult>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -229,6 +446,58 @@ pub enum TakeSeatError {
     CanOnlyHoldOne,
 }
 
+/// Reasons for `Game::standard` to refuse a game setup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RulesError {
+    /// Seat colors go from 1 to 4
+    InvalidSeatColor(u8),
+    /// Every seat color needs a komi
+    SeatColorWithoutKomi(u8),
+    /// Games have 1 to 7 seats
+    SeatCount(usize),
+    /// Games have 1 to 4 colors, each with a komi
+    KomiCount(usize),
+    /// Boards go from 1x1 to `MAX_BOARD_SIZE` on either side
+    BoardSize(u8, u8),
+    /// Zen go can't have more colors than there are komis
+    ZenColorCount(u8),
+    /// Two modifiers which don't work together, and why
+    IncompatibleModifiers(Cow<'static, str>, Cow<'static, str>, Cow<'static, str>),
+    /// Handicaps go from 2 to 9 stones in two color games. Fixed handicaps need a 9x9,
+    /// 13x13 or 19x19 board, with at most 5 stones on 9x9.
+    Handicap(u32),
+    /// The board doesn't fit the geometry
+    Geometry(Cow<'static, str>),
+    /// Capture targets start from one stone
+    CaptureTarget(u32),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RulesError::*;
+        match self {
+            InvalidSeatColor(color) => write!(f, "seat color {} is not between 1 and 4", color),
+            SeatColorWithoutKomi(color) => write!(f, "seat color {} has no komi", color),
+            SeatCount(count) => write!(f, "{} seats, only 1 to 7 are allowed", count),
+            KomiCount(count) => write!(f, "{} komis, only 1 to 4 are allowed", count),
+            BoardSize(width, height) => write!(
+                f,
+                "board size {}x{}, only sizes up to {size}x{size} are allowed",
+                width,
+                height,
+                size = MAX_BOARD_SIZE
+            ),
+            ZenColorCount(count) => write!(f, "zen go with {} colors needs a komi for each", count),
+            IncompatibleModifiers(a, b, reason) => {
+                write!(f, "{} can't be combined with {}: {}", a, b, reason)
+            }
+            Handicap(stones) => write!(f, "a handicap of {} stones doesn't fit this game", stones),
+            Geometry(reason) => write!(f, "invalid board: {}", reason),
+            CaptureTarget(stones) => write!(f, "a capture target of {} stones", stones),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MakeActionError {
     NotPlayer,
@@ -238,6 +507,11 @@ pub enum MakeActionError {
     Suicide,
     Ko,
     GameDone,
+    GameStarted,
+    UndoNotAllowed,
+    NoUndoRequest,
+    /// An undo request is still waiting for answers.
+    UndoPending,
 }
 
 pub enum ActionChange {
@@ -255,9 +529,7 @@ pub type MakeActionResult<T = ActionChange> = Result<T, MakeActionError>;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GameView {
-    // TODO: we need a separate state view since we have hidden information
-    // currently players can cheat :F
-    pub state: GameState,
+    pub state: GameStateView,
     pub seats: GroupVec<Seat>,
     pub turn: u32,
     pub board: Vec<Color>,
@@ -267,6 +539,9 @@ pub struct GameView {
     pub mods: GameModifier,
     pub points: GroupVec<i32>,
     pub move_number: u32,
+    /// Clocks as of the last action
+    pub clock: Option<ClockView>,
+    pub result: Option<GameResult>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -274,18 +549,10 @@ pub struct GameHistory {
     pub board: Vec<u8>,
     pub board_visibility: Option<Vec<u16>>,
     pub last_stone: Option<GroupVec<(u32, u32)>>,
+    pub ko_point: Option<Point>,
     pub move_number: u32,
 }
 
-#[derive(Serialize, Deserialize)]
-struct GameReplay {
-    actions: Vec<GameAction>,
-    mods: GameModifier,
-    komis: GroupVec<i32>,
-    seats: GroupVec<u8>,
-    size: (u8, u8),
-}
-
 ///////////////////////////////////////////////////////////////////////////////
 //                               Implementation                              //
 ///////////////////////////////////////////////////////////////////////////////
@@ -296,65 +563,99 @@ impl Game {
         komis: GroupVec<i32>,
         size: (u8, u8),
         mods: GameModifier,
-    ) -> Option<Game> {
-        if !seats.iter().all(|&t| t > 0 && t <= 4) {
-            return None;
+    ) -> Result<Game, RulesError> {
+        if let Some(&color) = seats.iter().find(|&&t| t == 0 || t > 4) {
+            return Err(RulesError::InvalidSeatColor(color));
         }
 
         // 7 = 3 colors, rengo
         // 4 = 4 colors
-        if !(1..=7).contains(&seats.len()) || !(1..=4).contains(&komis.len()) {
-            return None;
+        if !(1..=7).contains(&seats.len()) {
+            return Err(RulesError::SeatCount(seats.len()));
+        }
+        if !(1..=4).contains(&komis.len()) {
+            return Err(RulesError::KomiCount(komis.len()));
+        }
+        if let Some(&color) = seats.iter().find(|&&t| t as usize > komis.len()) {
+            return Err(RulesError::SeatColorWithoutKomi(color));
         }
 
         // Don't allow huge boards
-        if size.0 > 19 || size.1 > 19 {
-            return None;
+        if size.0 == 0 || size.1 == 0 || size.0 > MAX_BOARD_SIZE || size.1 > MAX_BOARD_SIZE {
+            return Err(RulesError::BoardSize(size.0, size.1));
         }
 
-        let board = Board::empty(size.0 as _, size.1 as _);
-        let state = if let Some(rules) = &mods.hidden_move {
-            GameState::free_placement(
-                seats.len(),
-                komis.len(),
-                board.clone(),
-                rules.teams_share_stones,
-            )
-        } else {
-            GameState::play(seats.len())
+        check_compatible(&mods)?;
+        let modifiers = Modifiers::new(&mods);
+        let mut setup = GameSetup {
+            seats,
+            komis,
+            board: Board::empty(size.0 as _, size.1 as _),
+            visibility: None,
+            turn: 0,
+            mods: &mods,
+        };
+        for modifier in modifiers.iter() {
+            modifier.setup(&mut setup)?;
+        }
+        let state = modifiers
+            .iter()
+            .find_map(|m| m.start_state(&setup))
+            .unwrap_or_else(|| GameState::play(seats.len()));
+        let GameSetup {
+            komis,
+            board,
+            visibility,
+            turn,
+            ..
+        } = setup;
+
+        let mut shared = SharedState {
+            seats: seats.iter().map(|&t| Seat::new(Color(t))).collect(),
+            seat_colors: seats.iter().copied().collect(),
+            points: komis.clone(),
+            captures: komis.iter().map(|_| 0).collect(),
+            turn,
+            pass_count: 0,
+            board: board.clone(),
+            board_visibility: visibility.clone(),
+            hash: zobrist::hash(&board),
+            visibility_hash: zobrist::visibility_hash(visibility.as_ref()),
+            chains: Chains::new(&board),
+            board_history: Vec::new(),
+            history_tip: Position {
+                board,
+                board_visibility: visibility,
+            },
+            capture_count: 0,
+            clock: mods
+                .time_control
+                .clone()
+                .map(|control| GameClock::new(control, seats.len())),
+            time: 0,
+            result: None,
+            komis,
+            mods,
+            modifiers,
+            rules: RuleVersions::CURRENT,
         };
+        shared.reset_history(GameState::play(seats.len()));
 
-        Some(Game {
+        Ok(Game {
             state,
             state_stack: Vec::new(),
-            shared: SharedState {
-                seats: seats.iter().map(|&t| Seat::new(Color(t))).collect(),
-                points: komis.clone(),
-                turn: 0,
-                pass_count: 0,
-                board: board.clone(),
-                board_visibility: None,
-                board_history: vec![BoardHistory {
-                    hash: board.hash(),
-                    board,
-                    board_visibility: None,
-                    state: GameState::play(seats.len()),
-                    points: komis.clone(),
-                    turn: 0,
-                }],
-                capture_count: 0,
-                komis,
-                mods,
-            },
+            shared,
             actions: vec![],
         })
     }
 
-    /// Loads a game from a replay dump. Can fail at any point due to changed rules...
-    /// Such is life.
+    /// Loads a game from a replay dump, upgrading older formats and replaying with the rule
+    /// versions the game was played under. Fails for dumps from a newer format.
     pub fn load(dump: &[u8]) -> Option<Game> {
-        let replay: GameReplay = serde_cbor::from_slice(dump).ok()?;
-        let mut game = Game::standard(&replay.seats, replay.komis, replay.size, replay.mods)?;
+        let replay = GameReplay::from_slice(dump)?;
+        let mut game =
+            Game::standard(&replay.seats, replay.komis, replay.size, replay.mods).ok()?;
+        game.shared.rules = replay.rules;
 
         for action in replay.actions {
             use ReplayActionKind::*;
@@ -366,11 +667,33 @@ impl Game {
                     game.leave_seat(action.user_id, seat_id as _).ok()?;
                 }
                 Play(play) => {
-                    game.make_action(action.user_id, play).ok()?;
+                    game.make_action_at(action.user_id, play, action.time)
+                        .ok()?;
+                }
+                Setup(setup) => {
+                    game.setup(setup).ok()?;
+                }
+                Timeout => {
+                    if !game.check_time(action.time) {
+                        return None;
+                    }
+                }
+                Forfeit(team) => {
+                    game.forfeit(Color(team), action.time).ok()?;
+                }
+                StartClock => {
+                    if !game.start_clock(action.time) {
+                        return None;
+                    }
                 }
             }
         }
 
+        // Keep the result the game ended with, even if the rules count it differently now.
+        if replay.result.is_some() {
+            game.shared.result = replay.result;
+        }
+
         Some(game)
     }
 
@@ -381,15 +704,87 @@ impl Game {
             actions: self.actions.clone(),
             komis: shared.komis.clone(),
             size: (shared.board.width as _, shared.board.height as _),
-            seats: shared.seats.iter().map(|x| x.team.0).collect(),
+            seats: shared.seat_colors.clone(),
             mods: shared.mods.clone(),
+            version: FORMAT_VERSION,
+            rules: shared.rules,
+            result: shared.result.clone(),
         };
 
-        let mut vec = Vec::new();
-        replay
-            .serialize(&mut serde_cbor::Serializer::new(&mut vec).packed_format())
-            .expect("Game dump failed");
-        vec
+        replay.to_vec()
+    }
+
+    /// Exports the game as SGF. Undone moves are dropped, variant specific data is kept in
+    /// custom properties. `nick` maps user ids to names for the player properties.
+    pub fn to_sgf(&self, nick: impl Fn(u64) -> Option<String>) -> String {
+        sgf::export(self, nick)
+    }
+
+    /// Imports a game from SGF, replaying every move of the main line.
+    pub fn from_sgf(sgf: &str) -> Result<Game, SgfError> {
+        sgf::import(sgf)
+    }
+
+    /// Creates a fresh game with the same setup, for replaying the actions step by step.
+    fn restarted(&self) -> Option<Game> {
+        let shared = &self.shared;
+        Game::standard(
+            &shared.seat_colors,
+            shared.komis.clone(),
+            (shared.board.width as _, shared.board.height as _),
+            shared.mods.clone(),
+        )
+        .ok()
+        .map(|mut game| {
+            game.shared.rules = shared.rules;
+            game
+        })
+    }
+
+    /// Places stones and picks the starting seat. Only allowed before the first move of a
+    /// game without a placement phase.
+    pub fn setup(&mut self, setup: Setup) -> Result<(), MakeActionError> {
+        let shared = &mut self.shared;
+
+        if !matches!(self.state, GameState::Play(_)) || shared.board_history.len() > 1 {
+            return Err(MakeActionError::GameStarted);
+        }
+
+        if setup.turn as usize >= shared.seats.len() {
+            return Err(MakeActionError::OutOfBounds);
+        }
+
+        let mut board = shared.board.clone();
+        for &(point, color) in &setup.stones {
+            if !board.point_within(point)
+                || color.is_empty()
+                || color.as_usize() > shared.komis.len()
+            {
+                return Err(MakeActionError::OutOfBounds);
+            }
+            *board.point_mut(point) = color;
+        }
+
+        shared.hash = zobrist::hash(&board);
+        shared.chains = Chains::new(&board);
+        shared.board = board;
+        shared.turn = setup.turn as usize;
+        if let Some(clock) = &mut shared.clock {
+            if let Some((_, since)) = clock.running {
+                clock.running = Some((shared.turn, since));
+            }
+        }
+
+        let state = shared.board_history[0].state.clone();
+        shared.reset_history(state);
+
+        // Setup isn't made by any player.
+        self.actions.push(GameAction::new(
+            0,
+            ReplayActionKind::Setup(setup),
+            shared.time,
+        ));
+        Ok(())
     }
 
     pub fn take_seat(&mut self, player_id: u64, seat_id: usize) -> Result<(), TakeSeatError> {
@@ -413,6 +808,7 @@ impl Game {
         self.actions.push(GameAction::new(
             player_id,
             ReplayActionKind::TakeSeat(seat_id as _),
+            shared.time,
         ));
         Ok(())
     }
@@ -430,6 +826,7 @@ impl Game {
         self.actions.push(GameAction::new(
             player_id,
             ReplayActionKind::LeaveSeat(seat_id as _),
+            shared.time,
         ));
         Ok(())
     }
@@ -439,6 +836,22 @@ impl Game {
         player_id: u64,
         action: ActionKind,
     ) -> Result<(), MakeActionError> {
+        self.make_action_at(player_id, action, self.shared.time)
+    }
+
+    /// Makes an action at `now` on the game clock. Times before the last action count as
+    /// the time of the last action.
+    pub fn make_action_at(
+        &mut self,
+        player_id: u64,
+        action: ActionKind,
+        now: Millis,
+    ) -> Result<(), MakeActionError> {
+        let now = now.max(self.shared.time);
+        if self.check_time(now) {
+            return Err(MakeActionError::GameDone);
+        }
+
         if !self
             .shared
             .seats
@@ -477,7 +890,17 @@ impl Game {
                     ActionChange::None => {}
                 }
 
-                self.actions.push(GameAction::play(player_id, action));
+                if let GameState::Done(scoring) = &self.state {
+                    if self.shared.result.is_none() {
+                        let has_winner = self.shared.modifiers.iter().all(|m| m.has_winner());
+                        self.shared.result =
+                            Some(GameResult::by_score(&scoring.scores, has_winner));
+                    }
+                }
+
+                self.shared.time = now;
+                self.update_clock(&action, now);
+                self.actions.push(GameAction::play(player_id, action, now));
 
                 Ok(())
             }
@@ -485,6 +908,93 @@ impl Game {
         }
     }
 
+    /// Starts the clock of the seat to move at `now`, for when play begins without an
+    /// action. Does nothing and returns false unless the game is in play with no clock
+    /// running. Placement phases start the clock with the action that ends them.
+    pub fn start_clock(&mut self, now: Millis) -> bool {
+        if !matches!(self.state, GameState::Play(_)) {
+            return false;
+        }
+        let clock = match &mut self.shared.clock {
+            Some(clock) if clock.running.is_none() => clock,
+            _ => return false,
+        };
+        let now = now.max(self.shared.time);
+        clock.start(self.shared.turn, now);
+        self.shared.time = now;
+        self.actions
+            .push(GameAction::new(0, ReplayActionKind::StartClock, now));
+        true
+    }
+
+    /// Charges the clock for an action made at `now` and starts the clock of the seat to
+    /// move. Clocks only run during play.
+    fn update_clock(&mut self, action: &ActionKind, now: Millis) {
+        let clock = match &mut self.shared.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+        let moved = matches!(action, ActionKind::Place(..) | ActionKind::Pass);
+        clock.stop(now, moved);
+        if matches!(self.state, GameState::Play(_)) {
+            clock.start(self.shared.turn, now);
+        }
+    }
+
+    /// Ends the game if the running clock is out of time at `now`. Returns true if it did.
+    pub fn check_time(&mut self, now: Millis) -> bool {
+        if !matches!(self.state, GameState::Play(_)) {
+            return false;
+        }
+        let clock = match &mut self.shared.clock {
+            Some(clock) => clock,
+            None => return false,
+        };
+        let seat = match clock.timed_out(now) {
+            Some(seat) => seat,
+            None => return false,
+        };
+        clock.stop(now, false);
+
+        let team = self.shared.seats[seat].team;
+        self.end_game(team, ResultReason::Timeout, now);
+        self.actions
+            .push(GameAction::new(0, ReplayActionKind::Timeout, now));
+        true
+    }
+
+    /// Ends the game as lost by `team`, without counting.
+    pub fn forfeit(&mut self, team: Color, now: Millis) -> Result<(), MakeActionError> {
+        if let GameState::Done(_) = self.state {
+            return Err(MakeActionError::GameDone);
+        }
+        if !self.shared.seats.iter().any(|x| x.team == team) {
+            return Err(MakeActionError::NotPlayer);
+        }
+        let now = now.max(self.shared.time);
+        if let Some(clock) = &mut self.shared.clock {
+            clock.stop(now, false);
+        }
+
+        self.end_game(team, ResultReason::Forfeit, now);
+        self.actions
+            .push(GameAction::new(0, ReplayActionKind::Forfeit(team.0), now));
+        Ok(())
+    }
+
+    fn end_game(&mut self, loser: Color, reason: ResultReason, now: Millis) {
+        let scoring = ScoringState::new(&self.shared);
+        self.shared.result = Some(GameResult::lost_by(loser, reason, &scoring.scores));
+        self.shared.time = now;
+        self.state = GameState::Done(scoring);
+    }
+
+    /// The clocks as they are at `now`, with the running one counted down.
+    pub fn clock_view(&self, now: Millis) -> Option<ClockView> {
+        let now = now.max(self.shared.time);
+        self.shared.clock.as_ref().map(|clock| clock.view(now))
+    }
+
     fn get_board_view(
         &self,
         player_id: u64,
@@ -496,89 +1006,29 @@ impl Game {
         let shared = &self.shared;
 
         let (board, board_visibility, hidden_stones_left) = match state {
-            GameState::FreePlacement(state) => {
-                if let Some((seat_idx, active_seat)) = shared
-                    .seats
-                    .iter()
-                    .enumerate()
-                    .find(|(_, x)| x.player == Some(player_id))
-                {
-                    let team = active_seat.team;
-
-                    let board = if state.teams_share_stones {
-                        &state.boards[team.0 as usize - 1]
-                    } else {
-                        &state.boards[seat_idx]
-                    };
-                    (board.points.clone(), None, 0)
-                } else {
-                    (shared.board.points.clone(), None, 0)
-                }
-            }
+            GameState::FreePlacement(state) => match state.view_index(&shared.seats, player_id) {
+                Some(idx) => (state.boards[idx].points.clone(), None, 0),
+                None => (shared.board.points.clone(), None, 0),
+            },
             GameState::Play(_) => {
-                let mut board = board.points.clone();
-                let board_visibility = board_visibility.clone();
-
-                let one_color = matches!(
-                    self.shared.mods.visibility_mode,
-                    Some(VisibilityMode::OneColor)
-                );
-
-                // Set color to white.
-                // TODO: Change this to black once the client supports selecting the color
-                const ONE_COLOR_TEAM: Color = Color(2);
+                let board = board.points.clone();
+                let board_visibility = board_visibility.clone().map(|x| x.points);
 
                 // If the game is done, everything is visible.
                 if game_done {
-                    return (board, board_visibility.map(|x| x.points), 0);
+                    return (board, board_visibility, 0);
                 }
 
-                if one_color {
-                    for p in &mut board {
-                        if !p.is_empty() {
-                            *p = ONE_COLOR_TEAM;
-                        }
-                    }
+                let team = state_view::team_of(shared, player_id);
+                let mut view = BoardView {
+                    board,
+                    visibility: board_visibility,
+                    hidden_stones_left: 0,
                 };
-
-                if let Some(active_seat) = shared.seats.iter().find(|x| x.player == Some(player_id))
-                {
-                    let team = if !one_color {
-                        active_seat.team
-                    } else {
-                        ONE_COLOR_TEAM
-                    };
-
-                    if let Some(mut visibility) = board_visibility {
-                        let mut hidden_stones_left = 0;
-                        for (board, visibility) in board.iter_mut().zip(&mut visibility.points) {
-                            if visibility.get(active_seat.team.as_usize()) {
-                                *board = team;
-                                if visibility.len() > 1 {
-                                    hidden_stones_left += 1;
-                                }
-                                *visibility = Bitmap::new();
-                                visibility.set(team.as_usize(), true);
-                            } else if !visibility.is_empty() {
-                                hidden_stones_left += 1;
-                                *board = Color::empty();
-                                *visibility = Bitmap::new();
-                            }
-                        }
-                        (board, Some(visibility.points), hidden_stones_left)
-                    } else {
-                        (board, None, 0)
-                    }
-                } else {
-                    if let Some(visibility) = &board_visibility {
-                        for (a, b) in board.iter_mut().zip(&visibility.points) {
-                            if !b.is_empty() {
-                                *a = Color::empty();
-                            }
-                        }
-                    }
-                    (board, None, 0)
+                for modifier in shared.modifiers.iter() {
+                    modifier.view(&mut view, team);
                 }
+                (view.board, view.visibility, view.hidden_stones_left)
             }
             GameState::Scoring(_) | GameState::Done(_) => (board.points.clone(), None, 0),
         };
@@ -597,7 +1047,7 @@ impl Game {
             game_done,
         );
         GameView {
-            state: self.state.clone(),
+            state: GameStateView::new(&self.state, shared, player_id),
             seats: shared.seats.clone(),
             turn: shared.turn as _,
             board,
@@ -607,36 +1057,159 @@ impl Game {
             mods: shared.mods.clone(),
             points: shared.points.clone(),
             move_number: shared.board_history.len() as u32 - 1,
+            clock: self.clock_view(shared.time),
+            result: shared.result.clone(),
         }
     }
 
     pub fn get_view_at(&self, player_id: u64, turn: u32) -> Option<GameHistory> {
         let shared = &self.shared;
-        let BoardHistory {
-            board,
-            state,
-            board_visibility,
-            ..
-        } = &shared.board_history.get(turn as usize)?;
-
         let game_done = matches!(self.state, GameState::Done(_));
 
         if !game_done && self.shared.mods.no_history {
             return None;
         }
 
+        let state = &shared.board_history.get(turn as usize)?.state;
+        let Position {
+            board,
+            board_visibility,
+        } = shared.position_at(turn as usize)?;
+
+        let play = state.assume::<PlayState>();
+        let last_stone = match game_done {
+            true => play.last_stone.clone(),
+            false => state_view::last_stone_view(
+                &play.last_stone,
+                board_visibility.as_ref(),
+                state_view::team_of(shared, player_id),
+            ),
+        };
         let (board, board_visibility, _hidden_stones_left) =
-            self.get_board_view(player_id, state, board, board_visibility, game_done);
+            self.get_board_view(player_id, state, &board, &board_visibility, game_done);
 
         Some(GameHistory {
             board: board.iter().map(|x| x.0).collect(),
             board_visibility: board_visibility.map(|b| b.iter().map(|x| x.into_value()).collect()),
-            last_stone: state.assume::<PlayState>().last_stone.clone(),
+            last_stone,
+            ko_point: play.ko_point,
             move_number: turn,
         })
     }
 }
 
+impl SharedState {
+    /// Adds the current position to the history, as changes to the previous entry.
+    pub fn push_history(&mut self, state: GameState) {
+        let keyframe = self.board_history.len().is_multiple_of(KEYFRAME_INTERVAL);
+        let tip = &mut self.history_tip;
+
+        let mut changes = Vec::new();
+        for (idx, (old, &new)) in tip
+            .board
+            .points
+            .iter_mut()
+            .zip(&self.board.points)
+            .enumerate()
+        {
+            if *old != new {
+                *old = new;
+                changes.push((
+                    self.board.idx_to_coord(idx).expect("Index within board"),
+                    new,
+                ));
+            }
+        }
+
+        let mut visibility_changes = Vec::new();
+        match (&mut tip.board_visibility, &self.board_visibility) {
+            (Some(old), Some(new)) => {
+                for (idx, (old, &new)) in old.points.iter_mut().zip(&new.points).enumerate() {
+                    if *old != new {
+                        *old = new;
+                        visibility_changes.push((
+                            self.board.idx_to_coord(idx).expect("Index within board"),
+                            new,
+                        ));
+                    }
+                }
+            }
+            (old, new) => *old = new.clone(),
+        }
+
+        let keyframe = if keyframe {
+            changes.clear();
+            visibility_changes.clear();
+            Some(Box::new(tip.clone()))
+        } else {
+            None
+        };
+
+        self.board_history.push(BoardHistory {
+            hash: self.hash,
+            visibility_hash: self.visibility_hash,
+            keyframe,
+            changes,
+            visibility_changes,
+            state,
+            points: self.points.clone(),
+            captures: self.captures.clone(),
+            turn: self.turn,
+        });
+    }
+
+    /// Starts the history over with the current position as the first entry.
+    pub fn reset_history(&mut self, state: GameState) {
+        self.board_history.clear();
+        self.history_tip = Position {
+            board: self.board.clone(),
+            board_visibility: self.board_visibility.clone(),
+        };
+        self.push_history(state);
+    }
+
+    /// Drops the last history entry. The tip goes back to the position before it.
+    pub fn pop_history(&mut self) -> Option<BoardHistory> {
+        let history = self.board_history.pop()?;
+        if let Some(position) = self.rebuild_position(self.board_history.len().wrapping_sub(1)) {
+            self.history_tip = position;
+        }
+        Some(history)
+    }
+
+    /// The position of a history entry.
+    pub fn position_at(&self, turn: usize) -> Option<Position> {
+        if turn + 1 == self.board_history.len() {
+            return Some(self.history_tip.clone());
+        }
+        self.rebuild_position(turn)
+    }
+
+    /// Rebuilds the position of a history entry from the keyframe before it.
+    fn rebuild_position(&self, turn: usize) -> Option<Position> {
+        let history = self.board_history.get(..=turn)?;
+        let start = history
+            .iter()
+            .rposition(|h| h.keyframe.is_some())
+            .expect("History should start with a keyframe");
+        let mut position = (**history[start].keyframe.as_ref().expect("Keyframe")).clone();
+
+        for entry in &history[start + 1..] {
+            for &(point, color) in &entry.changes {
+                *position.board.point_mut(point) = color;
+            }
+            if let Some(visibility) = &mut position.board_visibility {
+                for &(point, value) in &entry.visibility_changes {
+                    *visibility.point_mut(point) = value;
+                }
+            }
+        }
+
+        Some(position)
+    }
+}
+
+/// Finds every group of connected stones with its liberties. Each stone is listed once.
 pub fn find_groups(board: &Board) -> Vec<Group> {
     let mut legal_points = board
         .points
@@ -656,13 +1229,17 @@ pub fn find_groups(board: &Board) -> Vec<Group> {
     let mut groups = Vec::new();
 
     while let Some(point) = legal_points.pop() {
-        let mut group = Group::default();
-        group.alive = true;
-        group.team = board.get_point(point);
+        let mut group = Group {
+            alive: true,
+            team: board.get_point(point),
+            ..Group::default()
+        };
         if group.team.is_empty() {
             unreachable!("scanned an empty point");
         }
 
+        // The first stone counts as seen too, or its neighbours would add it again.
+        seen.insert(point);
         stack.push_back(point);
 
         while let Some(point) = stack.pop_front() {
@@ -689,3 +1266,389 @@ pub fn find_groups(board: &Board) -> Vec<Group> {
 
     groups
 }
+
+/// Star points for a fixed handicap, in the order they are traditionally filled.
+pub fn handicap_points(size: (u8, u8), stones: u32) -> Option<Vec<Point>> {
+    let (low, mid, high) = match size {
+        (9, 9) if stones <= 5 => (2, 4, 6),
+        (13, 13) => (3, 6, 9),
+        (19, 19) => (3, 9, 15),
+        _ => return None,
+    };
+    let corners = [(high, low), (low, high), (high, high), (low, low)];
+    let sides = [(low, mid), (high, mid), (mid, low), (mid, high)];
+    let center = (mid, mid);
+
+    let mut points = corners[..stones.min(4) as usize].to_vec();
+    match stones {
+        5 | 7 | 9 => {
+            points.extend_from_slice(&sides[..stones as usize - 5]);
+            points.push(center);
+        }
+        6 | 8 => points.extend_from_slice(&sides[..stones as usize - 4]),
+        _ => {}
+    }
+    Some(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handicap_sets_up_the_game() {
+        let mods = |stones, free_placement| GameModifier {
+            handicap: Some(Handicap {
+                stones,
+                free_placement,
+            }),
+            ..GameModifier::default()
+        };
+
+        let game =
+            Game::standard(&[1, 2], tinyvec::tiny_vec![0, 15], (19, 19), mods(3, false)).unwrap();
+        let black = game
+            .shared
+            .board
+            .points
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == Color(1))
+            .map(|(idx, _)| game.shared.board.idx_to_coord(idx).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(black, vec![(15, 3), (3, 15), (15, 15)]);
+        assert_eq!(game.shared.turn, 1);
+        assert_eq!(&game.shared.komis[..], &[0, 7]);
+
+        let mut game =
+            Game::standard(&[1, 2], tinyvec::tiny_vec![0, 15], (9, 9), mods(2, true)).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        assert_eq!(
+            game.make_action(2, ActionKind::Place(0, 0)),
+            Err(MakeActionError::NotTurn)
+        );
+        game.make_action(1, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(1, ActionKind::Cancel).unwrap();
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(1, ActionKind::Place(6, 6)).unwrap();
+        assert!(matches!(game.state, GameState::Play(_)));
+        assert_eq!(game.shared.board.get_point((0, 0)), Color::empty());
+        assert_eq!(game.shared.board.get_point((6, 6)), Color(1));
+        game.make_action(2, ActionKind::Place(4, 4)).unwrap();
+
+        let standard = |seats: &[u8], komis, size, mods| Game::standard(seats, komis, size, mods);
+        assert_eq!(
+            standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods(6, false)).err(),
+            Some(RulesError::Handicap(6))
+        );
+        assert_eq!(
+            standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods(6, true))
+                .map(|g| g.shared.komis),
+            Ok(tinyvec::tiny_vec![0, 13])
+        );
+        assert_eq!(
+            standard(
+                &[1, 2, 3],
+                tinyvec::tiny_vec![0, 0, 0],
+                (9, 9),
+                mods(2, true)
+            )
+            .err(),
+            Some(RulesError::Handicap(2))
+        );
+    }
+
+    #[test]
+    fn timeout_ends_the_game_and_replays() {
+        let mods = GameModifier {
+            time_control: Some(TimeControl::Absolute { main: 10_000 }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+
+        assert!(game.start_clock(48_000));
+        assert!(!game.start_clock(49_000));
+        game.make_action_at(1, ActionKind::Place(2, 2), 50_000)
+            .unwrap();
+        game.make_action_at(2, ActionKind::Place(6, 6), 54_000)
+            .unwrap();
+        assert_eq!(
+            game.clock_view(60_000).unwrap().remaining,
+            vec![2_000, 6_000]
+        );
+        assert!(!game.check_time(61_999));
+
+        assert_eq!(
+            game.make_action_at(1, ActionKind::Place(4, 4), 62_000),
+            Err(MakeActionError::GameDone)
+        );
+        assert!(matches!(game.state, GameState::Done(_)));
+        assert!(game.shared.clock.as_ref().unwrap().seats[0].timed_out);
+
+        let replay = Game::load(&game.dump()).unwrap();
+        assert!(matches!(replay.state, GameState::Done(_)));
+        assert_eq!(replay.shared.clock, game.shared.clock);
+        assert_eq!(replay.actions, game.actions);
+    }
+
+    #[test]
+    fn first_clock_runs_out_without_a_move() {
+        let mods = GameModifier {
+            time_control: Some(TimeControl::Absolute { main: 10_000 }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        assert!(game.start_clock(1_000));
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+
+        assert_eq!(game.clock_view(5_000).unwrap().running, Some(0));
+        assert!(game.check_time(3_600_000));
+        let result = game.shared.result.clone().unwrap();
+        assert_eq!(result.winner, Some(Color(2)));
+        assert_eq!(result.reason, ResultReason::Timeout);
+
+        let replay = Game::load(&game.dump()).unwrap();
+        assert_eq!(replay.shared.result, Some(result));
+        assert_eq!(replay.shared.clock, game.shared.clock);
+
+        // Handicap placement starts the clock with the move that ends it.
+        let mods = GameModifier {
+            time_control: Some(TimeControl::Absolute { main: 10_000 }),
+            handicap: Some(Handicap {
+                stones: 2,
+                free_placement: true,
+            }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        assert!(!game.start_clock(0));
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action_at(1, ActionKind::Place(2, 2), 1_000)
+            .unwrap();
+        game.make_action_at(1, ActionKind::Place(6, 6), 2_000)
+            .unwrap();
+        assert!(matches!(game.state, GameState::Play(_)));
+        assert_eq!(game.clock_view(5_000).unwrap().running, Some(1));
+        assert!(game.check_time(12_000));
+    }
+
+    #[test]
+    fn resigning_and_forfeiting_end_the_game() {
+        let mut game = Game::standard(
+            &[1, 2, 1, 2],
+            tinyvec::tiny_vec![0, 0],
+            (9, 9),
+            GameModifier::default(),
+        )
+        .unwrap();
+        for seat in 0..4 {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+
+        // The second white player resigns for the team, out of turn.
+        game.make_action(4, ActionKind::Resign).unwrap();
+        let result = game.shared.result.clone().unwrap();
+        assert_eq!(result.winner, Some(Color(1)));
+        assert_eq!(result.reason, ResultReason::Resignation);
+        assert_eq!(result.teams[1].team, Color(2));
+        assert_eq!(
+            game.make_action(3, ActionKind::Pass),
+            Err(MakeActionError::GameDone)
+        );
+        assert_eq!(
+            Game::load(&game.dump()).unwrap().shared.result,
+            Some(result)
+        );
+
+        let mut game = Game::standard(
+            &[1, 2, 3],
+            tinyvec::tiny_vec![0, 0, 0],
+            (9, 9),
+            GameModifier::default(),
+        )
+        .unwrap();
+        game.forfeit(Color(1), 0).unwrap();
+        let result = game.shared.result.clone().unwrap();
+        assert_eq!(result.reason, ResultReason::Forfeit);
+        let places = result.teams.iter().map(|x| (x.team.0, x.place));
+        assert_eq!(places.collect::<Vec<_>>(), vec![(2, 1), (3, 1), (1, 3)]);
+        assert_eq!(result.winner, Some(Color(2)));
+        assert_eq!(
+            Game::load(&game.dump()).unwrap().shared.result,
+            Some(result)
+        );
+    }
+
+    #[test]
+    fn standard_explains_refused_rules() {
+        let komis = || tinyvec::tiny_vec![0, 0];
+        let standard =
+            |seats: &[u8], komis, size, mods| Game::standard(seats, komis, size, mods).err();
+        let mods = GameModifier::default;
+
+        assert_eq!(standard(&[1, 2], komis(), (9, 9), mods()), None);
+        assert_eq!(
+            standard(&[1, 5], komis(), (9, 9), mods()),
+            Some(RulesError::InvalidSeatColor(5))
+        );
+        assert_eq!(
+            standard(&[1, 3], komis(), (9, 9), mods()),
+            Some(RulesError::SeatColorWithoutKomi(3))
+        );
+        assert_eq!(
+            standard(&[1, 2], komis(), (9, 38), mods()),
+            Some(RulesError::BoardSize(9, 38))
+        );
+        assert_eq!(
+            standard(&[], komis(), (9, 9), mods()),
+            Some(RulesError::SeatCount(0))
+        );
+
+        let zen = GameModifier {
+            zen_go: Some(ZenGo { color_count: 3 }),
+            ..mods()
+        };
+        assert_eq!(
+            standard(&[1, 1, 1], komis(), (9, 9), zen.clone()),
+            Some(RulesError::ZenColorCount(3))
+        );
+        let hidden_zen = GameModifier {
+            zen_go: Some(ZenGo { color_count: 2 }),
+            hidden_move: Some(HiddenMoveGo::default()),
+            ..mods()
+        };
+        assert!(matches!(
+            standard(&[1, 1, 1], komis(), (9, 9), hidden_zen),
+            Some(RulesError::IncompatibleModifiers(..))
+        ));
+    }
+
+    #[test]
+    fn rectangular_and_large_boards() {
+        use crate::message::ServerMessage;
+
+        for &(size, colors) in &[
+            ((9, 13), 2),
+            ((13, 9), 2),
+            ((1, 37), 2),
+            ((25, 25), 3),
+            ((37, 37), 3),
+        ] {
+            let seats = (1..=colors).collect::<Vec<u8>>();
+            let komis = seats.iter().map(|_| 0).collect();
+            let mut game = Game::standard(&seats, komis, size, GameModifier::default()).unwrap();
+            for seat in 0..seats.len() {
+                game.take_seat(seat as u64 + 1, seat).unwrap();
+            }
+
+            // Black takes the stone in the far corner, blue plays in the near one.
+            let (w, h) = (size.0 as u32, size.1 as u32);
+            let first = if w > 1 { (w - 2, h - 1) } else { (0, 0) };
+            let moves = [
+                (Color(1), first),
+                (Color(2), (w - 1, h - 1)),
+                (Color(3), (0, 0)),
+                (Color(1), (w - 1, h - 2)),
+            ];
+            for &(color, (x, y)) in moves.iter().filter(|(color, _)| color.0 <= colors) {
+                let player = color.0 as u64;
+                game.make_action(player, ActionKind::Place(x, y)).unwrap();
+            }
+            assert!(
+                game.shared.board.get_point((w - 1, h - 1)).is_empty(),
+                "{:?}",
+                size
+            );
+            assert_eq!(game.shared.captures[0], 1);
+
+            let view = game.get_view(1);
+            assert_eq!((view.size, view.board.len()), (size, (w * h) as usize));
+            let packed = ServerMessage::game_status(0, vec![1], view).pack();
+            match serde_cbor::from_slice(&packed).unwrap() {
+                ServerMessage::GameStatus {
+                    size: packed_size,
+                    board,
+                    ..
+                } => {
+                    assert_eq!(packed_size, size);
+                    assert_eq!(board[(w * h - 2) as usize], 1);
+                }
+                _ => unreachable!(),
+            }
+
+            let loaded = Game::load(&game.dump()).unwrap();
+            assert_eq!(loaded.shared.board, game.shared.board);
+            let imported = Game::from_sgf(&game.to_sgf(|_| None)).unwrap();
+            assert_eq!(imported.shared.board, game.shared.board, "{:?}", size);
+        }
+    }
+
+    #[test]
+    fn history_rebuilds_every_position() {
+        let mut game = Game::standard(
+            &[1, 2],
+            tinyvec::tiny_vec![0, 0],
+            (9, 9),
+            GameModifier::default(),
+        )
+        .unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+
+        // Pseudo random moves, with captures happening along the way.
+        let mut positions = vec![game.shared.board.clone()];
+        let mut seed = 7u32;
+        while positions.len() < 3 * KEYFRAME_INTERVAL {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let idx = (seed >> 8) % 81;
+            let player = game.shared.turn as u64 + 1;
+            if game
+                .make_action(player, ActionKind::Place(idx % 9, idx / 9))
+                .is_ok()
+            {
+                positions.push(game.shared.board.clone());
+            }
+        }
+
+        let history = &game.shared.board_history;
+        assert_eq!(history.len(), positions.len());
+        assert_eq!(history.iter().filter(|h| h.keyframe.is_some()).count(), 3);
+        for (turn, board) in positions.iter().enumerate() {
+            assert_eq!(&game.shared.position_at(turn).unwrap().board, board);
+        }
+
+        // Undo across a keyframe.
+        for _ in 0..KEYFRAME_INTERVAL + 1 {
+            let player = game.shared.turn as u64 + 1;
+            game.make_action(player, ActionKind::Cancel).unwrap();
+            positions.pop();
+            assert_eq!(&game.shared.board, positions.last().unwrap());
+            assert_eq!(game.shared.hash, zobrist::hash(&game.shared.board));
+        }
+    }
+
+    #[test]
+    fn find_groups_lists_each_stone_once() {
+        let mut board = Board::empty(5, 5);
+        for &point in &[(1, 1), (2, 1), (2, 2)] {
+            *board.point_mut(point) = Color(1);
+        }
+        *board.point_mut((3, 2)) = Color(2);
+
+        let mut groups = find_groups(&board);
+        groups.sort_by_key(|g| g.team.0);
+        assert_eq!(groups.len(), 2);
+        let mut points = groups[0].points.clone();
+        points.sort_unstable();
+        assert_eq!(&points[..], &[(1, 1), (2, 1), (2, 2)]);
+        assert_eq!(groups[0].liberties, 6);
+        assert_eq!(&groups[1].points[..], &[(3, 2)]);
+        assert_eq!(groups[1].liberties, 3);
+    }
+}
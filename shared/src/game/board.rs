@@ -1,30 +1,307 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Either;
 use serde::{Deserialize, Serialize};
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use super::{Color, GroupVec};
+
+/// How the edges of the board connect.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Topology {
+    #[default]
+    Plane,
+    /// The left and right edges are next to each other.
+    Cylinder,
+    /// Both pairs of opposite edges are next to each other.
+    Torus,
+}
+
+impl Topology {
+    fn wraps(self) -> (bool, bool) {
+        match self {
+            Topology::Plane => (false, false),
+            Topology::Cylinder => (true, false),
+            Topology::Torus => (true, true),
+        }
+    }
+}
+
+/// Which points the board has and which of them are next to each other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Geometry {
+    /// Four neighbours, fewer on the edges unless the topology wraps them.
+    #[default]
+    Square,
+    /// Six neighbours. Every row is shifted half a point right of the one above, so the
+    /// board is a rhombus and (x, y) is next to (x + 1, y - 1) and (x - 1, y + 1).
+    Hex,
+    /// The surface of a cube with n x n points on each face, unfolded into a cross on a
+    /// 4n x 3n board. Every point has four neighbours.
+    Cube,
+    /// Any points and links between them.
+    Graph(Graph),
+}
+
+/// A board made of `points`, the rest of the board is left out.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Graph {
+    pub points: Vec<Point>,
+    /// Pairs of indices into `points` which are next to each other
+    pub edges: Vec<(u32, u32)>,
+}
+
+impl Geometry {
+    /// The neighbours of every point on a `width` x `height` board, None for square boards
+    /// which work them out from the topology.
+    pub fn links(&self, width: u32, height: u32) -> Result<Option<Links>, &'static str> {
+        let within = |&(x, y): &Point| x < width && y < height;
+        let links = match self {
+            Geometry::Square => return Ok(None),
+            Geometry::Hex => Links::from_fn(width, height, |(x, y)| {
+                let (x, y) = (x as i32, y as i32);
+                [(-1, 0), (1, 0), (0, -1), (0, 1), (1, -1), (-1, 1)]
+                    .iter()
+                    .map(|&(dx, dy)| (x + dx, y + dy))
+                    .filter(|&(x, y)| x >= 0 && y >= 0)
+                    .map(|(x, y)| (x as u32, y as u32))
+                    .filter(within)
+                    .collect()
+            }),
+            Geometry::Cube => {
+                if !width.is_multiple_of(4) || width / 4 * 3 != height {
+                    return Err("a cube needs a 4n x 3n board");
+                }
+                cube_links(width / 4)
+            }
+            Geometry::Graph(graph) => {
+                let mut links = Links {
+                    width,
+                    neighbours: vec![None; (width * height) as usize],
+                };
+                for point in &graph.points {
+                    if !within(point) {
+                        return Err("a point of the graph is off the board");
+                    }
+                    let idx = links.idx(*point);
+                    let slot = &mut links.neighbours[idx];
+                    if slot.is_some() {
+                        return Err("the graph has the same point twice");
+                    }
+                    *slot = Some(GroupVec::new());
+                }
+                for &(a, b) in &graph.edges {
+                    let (a, b) = match (graph.points.get(a as usize), graph.points.get(b as usize))
+                    {
+                        (Some(&a), Some(&b)) if a != b => (a, b),
+                        _ => return Err("an edge of the graph doesn't join two of its points"),
+                    };
+                    links.link(a, b);
+                    links.link(b, a);
+                }
+                links
+            }
+        };
+        Ok(Some(links))
+    }
+}
+
+/// Neighbours of every point of a board that isn't a square grid.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Links {
+    width: u32,
+    /// Indexed like `Board::points`, None for points that aren't part of the board
+    neighbours: Vec<Option<GroupVec<Point>>>,
+}
+
+impl Links {
+    fn from_fn(width: u32, height: u32, f: impl Fn(Point) -> GroupVec<Point>) -> Links {
+        let neighbours = (0..width * height)
+            .map(|idx| Some(f((idx % width, idx / width))))
+            .collect();
+        Links { width, neighbours }
+    }
+
+    fn idx(&self, (x, y): Point) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn link(&mut self, from: Point, to: Point) {
+        let idx = self.idx(from);
+        let neighbours = self.neighbours[idx].as_mut().expect("Point on the board");
+        if !neighbours.contains(&to) {
+            neighbours.push(to);
+        }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        self.neighbours[self.idx(point)].is_some()
+    }
+
+    pub fn neighbours(&self, point: Point) -> &[Point] {
+        self.neighbours[self.idx(point)].as_deref().unwrap_or(&[])
+    }
+}
+
+type Vector = [i32; 3];
 
-use super::Color;
+/// Faces of the unfolded cube: their place in the cross, and the normal of the face and
+/// the directions of the board's x and y axes on the cube.
+const CUBE_FACES: [(Point, Vector, Vector, Vector); 6] = [
+    ((1, 0), [0, 1, 0], [1, 0, 0], [0, 0, 1]),
+    ((0, 1), [-1, 0, 0], [0, 0, 1], [0, -1, 0]),
+    ((1, 1), [0, 0, 1], [1, 0, 0], [0, -1, 0]),
+    ((2, 1), [1, 0, 0], [0, 0, -1], [0, -1, 0]),
+    ((3, 1), [0, 0, -1], [-1, 0, 0], [0, -1, 0]),
+    ((1, 2), [0, -1, 0], [1, 0, 0], [0, 0, -1]),
+];
+
+/// Links the points of an `n` x `n` x `n` cube. Points are placed in the middle of their
+/// square on the surface, in coordinates doubled so they stay whole numbers. A step off a
+/// face goes over the edge onto the face next to it.
+fn cube_links(n: u32) -> Links {
+    let size = n as i32;
+    let offset = |u: u32| 2 * u as i32 + 1 - size;
+    let add = |a: Vector, b: Vector, k: i32| [a[0] + k * b[0], a[1] + k * b[1], a[2] + k * b[2]];
+
+    let mut cells = Vec::new();
+    for &((fx, fy), normal, axis_x, axis_y) in &CUBE_FACES {
+        for v in 0..n {
+            for u in 0..n {
+                let centre = add(add([0; 3], normal, size), axis_x, offset(u));
+                let centre = add(centre, axis_y, offset(v));
+                cells.push(((fx * n + u, fy * n + v), centre, normal, axis_x, axis_y));
+            }
+        }
+    }
+    let point_at = cells
+        .iter()
+        .map(|&(point, centre, ..)| (centre, point))
+        .collect::<HashMap<_, _>>();
+
+    let mut links = Links {
+        width: 4 * n,
+        neighbours: vec![None; (12 * n * n) as usize],
+    };
+    for &(point, centre, normal, axis_x, axis_y) in &cells {
+        let neighbours = [axis_x, axis_y]
+            .iter()
+            .flat_map(|&axis| vec![axis, add([0; 3], axis, -1)])
+            .map(|step| {
+                let within_face = add(centre, step, 2);
+                let over_edge = add(add(centre, step, 1), normal, -1);
+                point_at
+                    .get(&within_face)
+                    .or_else(|| point_at.get(&over_edge))
+                    .copied()
+                    .expect("Every step lands on the cube")
+            })
+            .collect();
+        let idx = links.idx(point);
+        links.neighbours[idx] = Some(neighbours);
+    }
+    links
+}
 
 #[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Board<T = Color> {
     pub width: u32,
     pub height: u32,
     pub points: Vec<T>,
+    #[serde(default)]
+    pub topology: Topology,
+    /// Set for boards that aren't square grids, see `Geometry`.
+    #[serde(default)]
+    pub links: Option<Arc<Links>>,
 }
 
 pub type Point = (u32, u32);
 
+/// Points next to `p` on a `width` x `height` board, each once.
+pub fn neighbours(
+    width: u32,
+    height: u32,
+    topology: Topology,
+    links: Option<&Links>,
+    p: Point,
+) -> impl Iterator<Item = Point> + '_ {
+    match links {
+        Some(links) => Either::Left(links.neighbours(p).iter().copied()),
+        None => Either::Right(grid_neighbours(width, height, topology, p)),
+    }
+}
+
+fn grid_neighbours(
+    width: u32,
+    height: u32,
+    topology: Topology,
+    (x, y): Point,
+) -> impl Iterator<Item = Point> {
+    let (x, y) = (x as i32, y as i32);
+    let mut points = [None; 4];
+    for (idx, &(dx, dy)) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().enumerate() {
+        let point = wrap(width, height, topology, (x + dx, y + dy));
+        // Narrow wrapped boards can reach the same point both ways, or the point itself.
+        if point != Some((x as u32, y as u32)) && !points[..idx].contains(&point) {
+            points[idx] = point;
+        }
+    }
+    IntoIterator::into_iter(points).flatten()
+}
+
+fn wrap(width: u32, height: u32, topology: Topology, (x, y): (i32, i32)) -> Option<Point> {
+    let (wrap_x, wrap_y) = topology.wraps();
+    let x = if wrap_x {
+        x.rem_euclid(width as i32)
+    } else {
+        x
+    };
+    let y = if wrap_y {
+        y.rem_euclid(height as i32)
+    } else {
+        y
+    };
+    if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+        Some((x as u32, y as u32))
+    } else {
+        None
+    }
+}
+
 impl<T: Copy + Default> Board<T> {
     pub fn empty(width: u32, height: u32) -> Self {
         Board {
             width,
             height,
             points: vec![T::default(); (width * height) as usize],
+            topology: Topology::Plane,
+            links: None,
+        }
+    }
+
+    /// An empty board with the size and shape of this one.
+    pub fn blank<U: Copy + Default>(&self) -> Board<U> {
+        Board {
+            topology: self.topology,
+            links: self.links.clone(),
+            ..Board::empty(self.width, self.height)
         }
     }
 
+    /// The point at `point` once the wrapping edges are taken into account, None if that
+    /// is off the board.
+    pub fn wrap(&self, point: (i32, i32)) -> Option<Point> {
+        wrap(self.width, self.height, self.topology, point)
+    }
+
+    /// Whether `point` is on the board. Graph boards leave out some points of the grid.
     pub fn point_within(&self, (x, y): Point) -> bool {
-        (0..self.width).contains(&x) && (0..self.height).contains(&y)
+        (0..self.width).contains(&x)
+            && (0..self.height).contains(&y)
+            && self
+                .links
+                .as_ref()
+                .is_none_or(|links| links.contains((x, y)))
     }
 
     pub fn get_point(&self, (x, y): Point) -> T {
@@ -43,28 +320,13 @@ impl<T: Copy + Default> Board<T> {
         }
     }
 
-    pub fn surrounding_points(&self, p: Point) -> impl Iterator<Item = Point> {
-        let x = p.0 as i32;
-        let y = p.1 as i32;
-        let width = self.width;
-        let height = self.height;
-        [(-1, 0), (1, 0), (0, -1), (0, 1)]
-            .iter()
-            .filter_map(move |&(dx, dy)| {
-                if (x + dx) >= 0 && x + dx < width as i32 && (y + dy) >= 0 && y + dy < height as i32
-                {
-                    Some(((x + dx) as u32, (y + dy) as u32))
-                } else {
-                    None
-                }
-            })
-    }
-}
-
-impl<T: Hash> Board<T> {
-    pub fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        Hash::hash(&self, &mut hasher);
-        hasher.finish()
+    pub fn surrounding_points(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+        neighbours(
+            self.width,
+            self.height,
+            self.topology,
+            self.links.as_deref(),
+            p,
+        )
     }
 }
@@ -0,0 +1,252 @@
+//! Chains of connected stones and their liberties, updated stone by stone.
+//!
+//! Finding captures with `find_groups` means scanning the whole board twice per move.
+//! `Chains` is kept in `SharedState` instead and only touches the stones around a change.
+//! `find_groups` stays around as the reference implementation.
+
+use std::sync::Arc;
+
+use super::board::{neighbours, Links};
+use super::{Board, Color, Group, Point, Topology};
+
+/// Marks an empty point in `Chains::chain_of`.
+const NONE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chain {
+    pub color: Color,
+    pub stones: Vec<Point>,
+    pub liberties: Vec<Point>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Chains {
+    width: u32,
+    height: u32,
+    topology: Topology,
+    links: Option<Arc<Links>>,
+    /// Index into `chains` for every point of the board
+    chain_of: Vec<u32>,
+    chains: Vec<Chain>,
+    /// Slots of `chains` which have been merged or captured and can be reused
+    free: Vec<u32>,
+}
+
+/// Chains are fully determined by the board they are built from, which gets compared on
+/// its own. Chain indices depend on the order of moves, so they are not compared.
+impl PartialEq for Chains {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Chains {
+    pub fn new(board: &Board) -> Chains {
+        let mut chains = Chains {
+            width: board.width,
+            height: board.height,
+            topology: board.topology,
+            links: board.links.clone(),
+            chain_of: vec![NONE; board.points.len()],
+            chains: Vec::new(),
+            free: Vec::new(),
+        };
+        for (idx, &color) in board.points.iter().enumerate() {
+            if !color.is_empty() {
+                let point = board.idx_to_coord(idx).expect("Index within board");
+                chains.add_stone(point, color);
+            }
+        }
+        chains
+    }
+
+    fn idx(&self, (x, y): Point) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    /// Borrows only the shape of the board, so the chains can change while iterating.
+    fn neighbours<'a>(
+        (width, height, topology): (u32, u32, Topology),
+        links: &'a Option<Arc<Links>>,
+        point: Point,
+    ) -> impl Iterator<Item = Point> + 'a {
+        neighbours(width, height, topology, links.as_deref(), point)
+    }
+
+    fn shape(&self) -> (u32, u32, Topology) {
+        (self.width, self.height, self.topology)
+    }
+
+    pub fn chain_at(&self, point: Point) -> Option<&Chain> {
+        match self.chain_of[self.idx(point)] {
+            NONE => None,
+            id => Some(&self.chains[id as usize]),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Chain> {
+        self.chains.iter().filter(|c| !c.stones.is_empty())
+    }
+
+    /// Adds a stone to an empty point, merging it with the chains of the same color next
+    /// to it and taking the point from the liberties of every chain around it.
+    pub fn add_stone(&mut self, point: Point, color: Color) {
+        let mut liberties = Vec::new();
+        let mut same_color = Vec::new();
+        for n in Chains::neighbours(self.shape(), &self.links, point) {
+            match self.chain_of[self.idx(n)] {
+                NONE => liberties.push(n),
+                id => {
+                    let chain = &mut self.chains[id as usize];
+                    chain.liberties.retain(|&l| l != point);
+                    if chain.color == color && !same_color.contains(&id) {
+                        same_color.push(id);
+                    }
+                }
+            }
+        }
+
+        let chain = Chain {
+            color,
+            stones: vec![point],
+            liberties,
+        };
+        let mut id = match self.free.pop() {
+            Some(id) => {
+                self.chains[id as usize] = chain;
+                id
+            }
+            None => {
+                self.chains.push(chain);
+                self.chains.len() as u32 - 1
+            }
+        };
+        let idx = self.idx(point);
+        self.chain_of[idx] = id;
+
+        for other in same_color {
+            id = self.merge(id, other);
+        }
+    }
+
+    /// Merges the smaller chain into the bigger one and returns the index of the result.
+    fn merge(&mut self, a: u32, b: u32) -> u32 {
+        let (big, small) =
+            if self.chains[a as usize].stones.len() >= self.chains[b as usize].stones.len() {
+                (a, b)
+            } else {
+                (b, a)
+            };
+
+        let small_chain = std::mem::take(&mut self.chains[small as usize]);
+        self.free.push(small);
+
+        for &stone in &small_chain.stones {
+            let idx = self.idx(stone);
+            self.chain_of[idx] = big;
+        }
+
+        let big_chain = &mut self.chains[big as usize];
+        big_chain.stones.extend(small_chain.stones);
+        for liberty in small_chain.liberties {
+            if !big_chain.liberties.contains(&liberty) {
+                big_chain.liberties.push(liberty);
+            }
+        }
+
+        big
+    }
+
+    /// Removes the whole chain at `point`, giving its stones as liberties to the chains
+    /// around it.
+    pub fn remove_chain(&mut self, point: Point) -> Chain {
+        let id = self.chain_of[self.idx(point)];
+        assert!(id != NONE, "no chain at {:?}", point);
+
+        let chain = std::mem::take(&mut self.chains[id as usize]);
+        self.free.push(id);
+
+        for &stone in &chain.stones {
+            let idx = self.idx(stone);
+            self.chain_of[idx] = NONE;
+        }
+        for &stone in &chain.stones {
+            for n in Chains::neighbours(self.shape(), &self.links, stone) {
+                let other = self.chain_of[self.idx(n)];
+                if other == NONE {
+                    continue;
+                }
+                let liberties = &mut self.chains[other as usize].liberties;
+                if !liberties.contains(&stone) {
+                    liberties.push(stone);
+                }
+            }
+        }
+
+        chain
+    }
+
+    /// The chains in the same form as `find_groups` returns them.
+    pub fn to_groups(&self) -> Vec<Group> {
+        self.iter()
+            .map(|chain| Group {
+                points: chain.stones.iter().copied().collect(),
+                liberties: chain.liberties.len() as i32,
+                team: chain.color,
+                alive: true,
+                ..Group::default()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::find_groups;
+
+    fn sorted(mut groups: Vec<Group>) -> Vec<Group> {
+        for group in &mut groups {
+            group.points.sort_unstable();
+        }
+        groups.sort_by_key(|g| g.points[0]);
+        groups
+    }
+
+    #[test]
+    fn matches_find_groups() {
+        // A deterministic jumble of stones with merges and captures.
+        let mut board = Board::empty(9, 7);
+        let mut chains = Chains::new(&board);
+        let mut seed = 12345u32;
+        for i in 0..200 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let idx = (seed >> 8) as usize % board.points.len();
+            let point = board.idx_to_coord(idx).unwrap();
+            if !board.get_point(point).is_empty() {
+                continue;
+            }
+            let color = Color(i % 3 + 1);
+            *board.point_mut(point) = color;
+            chains.add_stone(point, color);
+
+            let dead = chains
+                .iter()
+                .filter(|c| c.liberties.is_empty())
+                .map(|c| c.stones[0])
+                .collect::<Vec<_>>();
+            for stone in dead {
+                for p in chains.remove_chain(stone).stones {
+                    *board.point_mut(p) = Color::empty();
+                }
+            }
+
+            assert_eq!(sorted(chains.to_groups()), sorted(find_groups(&board)));
+        }
+
+        assert_eq!(
+            sorted(Chains::new(&board).to_groups()),
+            sorted(find_groups(&board))
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Game clocks.
+//!
+//! Clocks never read the time themselves, every call is given the current time. That keeps
+//! them deterministic, so replays end up with the same clocks and timeouts as the game did.
+//! Times are milliseconds from any fixed point, the server uses the unix epoch.
+
+use serde::{Deserialize, Serialize};
+
+pub type Millis = u64;
+
+/// Where the server gets the time for clocks from. Tests can use a fake one.
+pub trait TimeSource {
+    fn now(&self) -> Millis;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TimeControl {
+    /// `main` for the whole game.
+    Absolute { main: Millis },
+    /// `increment` is added to the clock after every move.
+    Fischer { main: Millis, increment: Millis },
+    /// After main time, every move has to be made within `period`. A move taking longer
+    /// uses up a period, the game is lost once they run out.
+    ByoYomi {
+        main: Millis,
+        periods: u32,
+        period: Millis,
+    },
+    /// After main time, `stones` moves have to be made within `period`, which then starts
+    /// over.
+    Canadian {
+        main: Millis,
+        stones: u32,
+        period: Millis,
+    },
+}
+
+impl TimeControl {
+    fn main(&self) -> Millis {
+        match *self {
+            TimeControl::Absolute { main }
+            | TimeControl::Fischer { main, .. }
+            | TimeControl::ByoYomi { main, .. }
+            | TimeControl::Canadian { main, .. } => main,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SeatClock {
+    /// Main time left
+    pub main: Millis,
+    /// Byo-yomi periods left, including the current one
+    pub periods: u32,
+    /// Time left in the current byo-yomi or Canadian period
+    pub period: Millis,
+    /// Moves left to make in the current Canadian period
+    pub stones: u32,
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameClock {
+    pub control: TimeControl,
+    pub seats: Vec<SeatClock>,
+    /// The seat whose clock is running, and since when
+    pub running: Option<(usize, Millis)>,
+}
+
+/// Clock times at one moment, for displaying.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockView {
+    /// Time each seat has left before timing out
+    pub remaining: Vec<Millis>,
+    pub seats: Vec<SeatClock>,
+    pub running: Option<usize>,
+}
+
+impl GameClock {
+    pub fn new(control: TimeControl, seat_count: usize) -> GameClock {
+        let (periods, period, stones) = match control {
+            TimeControl::ByoYomi {
+                periods, period, ..
+            } => (periods, period, 0),
+            TimeControl::Canadian { stones, period, .. } => (0, period, stones),
+            _ => (0, 0, 0),
+        };
+        let seat = SeatClock {
+            main: control.main(),
+            periods,
+            period,
+            stones,
+            timed_out: false,
+        };
+        GameClock {
+            control,
+            seats: vec![seat; seat_count],
+            running: None,
+        }
+    }
+
+    /// Starts the clock of `seat`. Only one clock runs at a time, so the running one is
+    /// stopped without counting a move.
+    pub fn start(&mut self, seat: usize, now: Millis) {
+        self.stop(now, false);
+        self.running = Some((seat, now));
+    }
+
+    /// Stops the running clock, charging it for the time used. `moved` gives the seat its
+    /// increment or starts its period over. Returns false if the clock ran out.
+    pub fn stop(&mut self, now: Millis, moved: bool) -> bool {
+        let (seat, since) = match self.running {
+            Some(x) => x,
+            None => return true,
+        };
+        // Using up exactly the time left is already too late.
+        let out_of_time = self.remaining(seat, now) == 0;
+        self.running = None;
+        let used = now.saturating_sub(since);
+        let in_time = spend(&self.control, &mut self.seats[seat], used, moved) && !out_of_time;
+        if !in_time {
+            self.seats[seat].timed_out = true;
+        }
+        in_time
+    }
+
+    /// Time `seat` has left at `now` before it times out.
+    pub fn remaining(&self, seat: usize, now: Millis) -> Millis {
+        let clock = &self.seats[seat];
+        let total = match self.control {
+            TimeControl::Absolute { .. } | TimeControl::Fischer { .. } => clock.main,
+            TimeControl::ByoYomi { period, .. } => {
+                let later_periods = clock.periods.saturating_sub(1) as Millis * period;
+                clock.main
+                    + if clock.periods > 0 {
+                        clock.period + later_periods
+                    } else {
+                        0
+                    }
+            }
+            TimeControl::Canadian { .. } => clock.main + clock.period,
+        };
+        match self.running {
+            Some((running, since)) if running == seat => {
+                total.saturating_sub(now.saturating_sub(since))
+            }
+            _ => total,
+        }
+    }
+
+    /// The running seat, if it is out of time at `now`.
+    pub fn timed_out(&self, now: Millis) -> Option<usize> {
+        let (seat, _) = self.running?;
+        if self.remaining(seat, now) == 0 {
+            Some(seat)
+        } else {
+            None
+        }
+    }
+
+    pub fn view(&self, now: Millis) -> ClockView {
+        ClockView {
+            remaining: (0..self.seats.len())
+                .map(|seat| self.remaining(seat, now))
+                .collect(),
+            seats: self.seats.clone(),
+            running: self.running.map(|(seat, _)| seat),
+        }
+    }
+}
+
+/// Takes `used` off a seat clock. Returns false if it wasn't enough.
+fn spend(control: &TimeControl, clock: &mut SeatClock, mut used: Millis, moved: bool) -> bool {
+    let from_main = used.min(clock.main);
+    clock.main -= from_main;
+    used -= from_main;
+
+    match *control {
+        TimeControl::Absolute { .. } => used == 0,
+        TimeControl::Fischer { increment, .. } => {
+            if used > 0 {
+                return false;
+            }
+            if moved {
+                clock.main += increment;
+            }
+            true
+        }
+        TimeControl::ByoYomi { period, .. } => {
+            // Every period used up completely is lost.
+            while used > 0 {
+                if used < clock.period {
+                    clock.period -= used;
+                    used = 0;
+                } else {
+                    used -= clock.period;
+                    clock.periods = clock.periods.saturating_sub(1);
+                    clock.period = period;
+                    if clock.periods == 0 {
+                        return false;
+                    }
+                }
+            }
+            if moved && clock.main == 0 {
+                clock.period = period;
+            }
+            true
+        }
+        TimeControl::Canadian { stones, period, .. } => {
+            if used >= clock.period && used > 0 {
+                return false;
+            }
+            clock.period -= used;
+            if moved && clock.main == 0 {
+                clock.stones = clock.stones.saturating_sub(1);
+                if clock.stones == 0 {
+                    clock.stones = stones;
+                    clock.period = period;
+                }
+            }
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays moves for seat 0 and 1 alternately, each taking the given time.
+    fn play(clock: &mut GameClock, moves: &[Millis]) -> Millis {
+        let mut now = 1_000;
+        clock.start(0, now);
+        for (i, &used) in moves.iter().enumerate() {
+            now += used;
+            assert!(clock.stop(now, true), "move {} timed out", i);
+            clock.start((i + 1) % 2, now);
+        }
+        now
+    }
+
+    #[test]
+    fn absolute_and_fischer() {
+        let mut clock = GameClock::new(TimeControl::Absolute { main: 10_000 }, 2);
+        let now = play(&mut clock, &[4_000, 1_000, 5_000]);
+        assert_eq!(clock.remaining(0, now), 1_000);
+        assert_eq!(clock.timed_out(now + 8_999), None);
+        assert_eq!(clock.timed_out(now + 9_000), Some(1));
+
+        let control = TimeControl::Fischer {
+            main: 10_000,
+            increment: 3_000,
+        };
+        let mut clock = GameClock::new(control, 2);
+        let now = play(&mut clock, &[4_000, 1_000, 5_000]);
+        assert_eq!(clock.remaining(0, now), 7_000);
+        assert_eq!(clock.remaining(1, now), 12_000);
+    }
+
+    #[test]
+    fn byo_yomi_loses_periods() {
+        let control = TimeControl::ByoYomi {
+            main: 5_000,
+            periods: 3,
+            period: 2_000,
+        };
+        let mut clock = GameClock::new(control, 2);
+        // Main time runs out, then a move within a period keeps all of them.
+        let now = play(&mut clock, &[6_000, 0, 1_500]);
+        assert_eq!((clock.seats[0].periods, clock.seats[0].period), (3, 2_000));
+
+        // Taking over two periods uses up two.
+        clock.start(0, now);
+        assert!(clock.stop(now + 4_500, true));
+        assert_eq!(clock.seats[0].periods, 1);
+        assert_eq!(clock.remaining(0, now), 2_000);
+
+        clock.start(0, now);
+        assert_eq!(clock.timed_out(now + 2_000), Some(0));
+        assert!(!clock.stop(now + 2_000, true));
+        assert!(clock.seats[0].timed_out);
+    }
+
+    #[test]
+    fn canadian_period_starts_over() {
+        let control = TimeControl::Canadian {
+            main: 1_000,
+            stones: 2,
+            period: 10_000,
+        };
+        let mut clock = GameClock::new(control, 2);
+        let now = play(&mut clock, &[2_000, 0, 3_000, 0, 4_000]);
+        // Two moves made it in time, the period starts over for the third.
+        assert_eq!((clock.seats[0].stones, clock.seats[0].period), (1, 6_000));
+        assert_eq!(clock.remaining(0, now), 6_000);
+
+        clock.start(0, now);
+        assert!(!clock.stop(now + 6_000, true));
+    }
+}
@@ -0,0 +1,323 @@
+//! Which variants can be combined.
+//!
+//! Every pair of variants is supported unless it is listed in `INCOMPATIBLE`, and the
+//! supported pairs are played through by the tests below. `Game::standard` refuses games
+//! combining an unsupported pair.
+
+use super::{GameModifier, Geometry, RulesError, Topology, VisibilityMode};
+
+/// The variants of `GameModifier`. Rules like the ko rule or scoring work with everything
+/// and aren't listed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Variant {
+    Pixel,
+    Ponnuki,
+    ZenGo,
+    HiddenMove,
+    OneColor,
+    Phantom,
+    NoHistory,
+    NPlusOne,
+    Handicap,
+    Topology,
+    Geometry,
+    CaptureTarget,
+}
+
+/// Pairs of variants that don't work together, and why.
+const INCOMPATIBLE: &[(Variant, Variant, &str)] = &[
+    (
+        Variant::ZenGo,
+        Variant::HiddenMove,
+        "hidden stones belong to a team, and zen go changes teams every move",
+    ),
+    (
+        Variant::Handicap,
+        Variant::HiddenMove,
+        "both place stones before the game",
+    ),
+    (
+        Variant::Handicap,
+        Variant::ZenGo,
+        "zen go has no black to give the stones to",
+    ),
+    (
+        Variant::Handicap,
+        Variant::Pixel,
+        "the komi makes up for single stones, not blobs",
+    ),
+    (
+        Variant::Handicap,
+        Variant::Topology,
+        "a board that wraps around has no corners for the star points",
+    ),
+    (
+        Variant::Handicap,
+        Variant::Geometry,
+        "the star points are only known for square boards",
+    ),
+    (
+        Variant::Topology,
+        Variant::Geometry,
+        "the geometry already says how the edges connect",
+    ),
+    (
+        Variant::Pixel,
+        Variant::Geometry,
+        "blobs are squares of the grid",
+    ),
+    (
+        Variant::NPlusOne,
+        Variant::Geometry,
+        "lines follow the rows and columns of the grid",
+    ),
+    (
+        Variant::ZenGo,
+        Variant::CaptureTarget,
+        "zen go has no winner",
+    ),
+    (
+        Variant::HiddenMove,
+        Variant::Phantom,
+        "hidden move go shows the stones you run into to everyone",
+    ),
+    (
+        Variant::ZenGo,
+        Variant::Phantom,
+        "phantom stones belong to a team, and zen go changes teams every move",
+    ),
+];
+
+impl Variant {
+    pub const ALL: [Variant; 12] = [
+        Variant::Pixel,
+        Variant::Ponnuki,
+        Variant::ZenGo,
+        Variant::HiddenMove,
+        Variant::OneColor,
+        Variant::Phantom,
+        Variant::NoHistory,
+        Variant::NPlusOne,
+        Variant::Handicap,
+        Variant::Topology,
+        Variant::Geometry,
+        Variant::CaptureTarget,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Pixel => "Pixel go",
+            Variant::Ponnuki => "Ponnuki is points",
+            Variant::ZenGo => "Zen go",
+            Variant::HiddenMove => "Hidden move go",
+            Variant::OneColor => "One color go",
+            Variant::Phantom => "Phantom go",
+            Variant::NoHistory => "No history",
+            Variant::NPlusOne => "N+1",
+            Variant::Handicap => "Handicap",
+            Variant::Topology => "Wrapping board",
+            Variant::Geometry => "Board geometry",
+            Variant::CaptureTarget => "Capture target",
+        }
+    }
+
+    /// The variants `mods` turns on, in `ALL` order.
+    pub fn enabled(mods: &GameModifier) -> Vec<Variant> {
+        Variant::ALL
+            .iter()
+            .copied()
+            .filter(|variant| match variant {
+                Variant::Pixel => mods.pixel,
+                Variant::Ponnuki => mods.ponnuki_is_points.is_some(),
+                Variant::ZenGo => mods.zen_go.is_some(),
+                Variant::HiddenMove => mods.hidden_move.is_some(),
+                Variant::OneColor => mods.visibility_mode == Some(VisibilityMode::OneColor),
+                Variant::Phantom => mods.visibility_mode == Some(VisibilityMode::Phantom),
+                Variant::NoHistory => mods.no_history,
+                Variant::NPlusOne => mods.n_plus_one.is_some(),
+                Variant::Handicap => mods.handicap.is_some(),
+                Variant::Topology => mods.topology != Topology::Plane,
+                Variant::Geometry => mods.geometry != Geometry::Square,
+                Variant::CaptureTarget => mods.capture_target.is_some(),
+            })
+            .collect()
+    }
+
+    /// Why `self` can't be combined with `other`, if it can't.
+    pub fn incompatibility(self, other: Variant) -> Option<&'static str> {
+        INCOMPATIBLE
+            .iter()
+            .find(|&&(a, b, _)| (a, b) == (self, other) || (b, a) == (self, other))
+            .map(|&(_, _, reason)| reason)
+    }
+}
+
+/// Fails for the first unsupported pair of variants in `mods`.
+pub fn check_compatible(mods: &GameModifier) -> Result<(), RulesError> {
+    let enabled = Variant::enabled(mods);
+    for (idx, &a) in enabled.iter().enumerate() {
+        for &b in &enabled[idx + 1..] {
+            if let Some(reason) = a.incompatibility(b) {
+                return Err(RulesError::IncompatibleModifiers(
+                    a.name().into(),
+                    b.name().into(),
+                    reason.into(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        ActionKind, CaptureTarget, Game, GameState, Handicap, HiddenMoveGo, NPlusOne, ZenGo,
+    };
+
+    fn enable(mods: &mut GameModifier, variant: Variant) {
+        match variant {
+            Variant::Pixel => mods.pixel = true,
+            Variant::Ponnuki => mods.ponnuki_is_points = Some(30),
+            Variant::ZenGo => mods.zen_go = Some(ZenGo { color_count: 2 }),
+            Variant::HiddenMove => {
+                mods.hidden_move = Some(HiddenMoveGo {
+                    placement_count: 2,
+                    teams_share_stones: true,
+                })
+            }
+            Variant::OneColor => mods.visibility_mode = Some(VisibilityMode::OneColor),
+            Variant::Phantom => mods.visibility_mode = Some(VisibilityMode::Phantom),
+            Variant::NoHistory => mods.no_history = true,
+            Variant::NPlusOne => mods.n_plus_one = Some(NPlusOne { length: 3 }),
+            Variant::Handicap => {
+                mods.handicap = Some(Handicap {
+                    stones: 2,
+                    free_placement: false,
+                })
+            }
+            Variant::Topology => mods.topology = Topology::Torus,
+            Variant::Geometry => mods.geometry = Geometry::Hex,
+            Variant::CaptureTarget => mods.capture_target = Some(CaptureTarget { stones: 1 }),
+        }
+    }
+
+    /// Plays a game through: hidden placements, moves with captures and lines, passes and
+    /// accepting the score, unless a capture ends it first. Every seat has its own player.
+    fn play_through(mods: GameModifier) -> Game {
+        let seats: &[u8] = if mods.zen_go.is_some() {
+            &[1, 2, 1]
+        } else {
+            &[1, 2]
+        };
+        let komis = tinyvec::tiny_vec![0, 0];
+        let mut game = Game::standard(seats, komis, (9, 9), mods).unwrap();
+        for seat in 0..seats.len() {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+
+        if let GameState::FreePlacement(_) = game.state {
+            let placements = [[(8, 8), (8, 7)], [(0, 8), (1, 8)]];
+            for (player, points) in [1, 2].iter().zip(&placements) {
+                for &(x, y) in points {
+                    game.make_action(*player, ActionKind::Place(x, y)).unwrap();
+                }
+                game.make_action(*player, ActionKind::Pass).unwrap();
+            }
+        }
+        assert!(matches!(game.state, GameState::Play(_)));
+
+        // Black surrounds a white stone at (1, 1) and white builds a line along the side.
+        // Pixel stones are 2x2 blobs, so each point stands for a block of the board there.
+        let moves = [
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (3, 3),
+            (2, 1),
+            (3, 2),
+            (1, 2),
+            (3, 0),
+            (0, 3),
+            (3, 1),
+        ];
+        let pixel = game.shared.mods.pixel;
+        for &(x, y) in &moves {
+            if let GameState::Done(_) = game.state {
+                break;
+            }
+            let player = game.shared.seats[game.shared.turn].player.unwrap();
+            let action = if pixel {
+                ActionKind::Place(2 * x + 1, 2 * y + 1)
+            } else {
+                ActionKind::Place(x, y)
+            };
+            assert!(
+                game.is_legal(player, &action),
+                "{:?} with {:?}",
+                action,
+                Variant::enabled(&game.shared.mods)
+            );
+            game.make_action(player, action).unwrap();
+        }
+
+        // Pass until scoring, then everyone accepts.
+        for _ in 0..8 {
+            let player = game.shared.seats[game.shared.turn].player.unwrap();
+            match game.state {
+                GameState::Play(_) => game.make_action(player, ActionKind::Pass).unwrap(),
+                GameState::Scoring(_) => {
+                    for player in 1..=seats.len() as u64 {
+                        let _ = game.make_action(player, ActionKind::Pass);
+                    }
+                }
+                _ => break,
+            }
+        }
+        game
+    }
+
+    #[test]
+    fn supported_pairs_play_through() {
+        for (idx, &a) in Variant::ALL.iter().enumerate() {
+            for &b in &Variant::ALL[idx + 1..] {
+                let mut mods = GameModifier::default();
+                enable(&mut mods, a);
+                enable(&mut mods, b);
+
+                // A game has a single visibility mode, so these two can't even be picked
+                // together.
+                if Variant::enabled(&mods) != [a, b] {
+                    assert_eq!((a, b), (Variant::OneColor, Variant::Phantom));
+                    continue;
+                }
+
+                if a.incompatibility(b).is_some() {
+                    assert!(
+                        matches!(
+                            Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods),
+                            Err(RulesError::IncompatibleModifiers(..))
+                        ),
+                        "{:?} + {:?}",
+                        a,
+                        b
+                    );
+                    continue;
+                }
+
+                let game = play_through(mods);
+                assert!(
+                    matches!(game.state, GameState::Done(_)),
+                    "{:?} + {:?}",
+                    a,
+                    b
+                );
+                assert!(game.shared.result.is_some());
+                let loaded = Game::load(&game.dump()).unwrap();
+                assert_eq!(loaded.shared.board, game.shared.board, "{:?} + {:?}", a, b);
+                assert_eq!(loaded.shared.points, game.shared.points);
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Legal move checks.
+//!
+//! These answer what `make_action` would accept without running it, so nothing is mutated
+//! and no `Game` has to be cloned. Placements are played out on a copy of the board as the
+//! player knows it: stones hidden from them count as empty points, and in hidden move games
+//! only the public ko point is checked, since comparing whole positions would tell where
+//! the hidden stones are. A move can still turn out to reveal a stone instead.
+
+use super::{
+    zobrist, ActionKind, Board, Chains, Color, Game, GameState, GroupVec, MakeActionError,
+    MakeActionResult, Point, SharedState, UndoPolicy,
+};
+use crate::modifiers;
+use crate::states::play::{last_mover, repeats_position};
+use crate::states::{FreePlacement, PlayState};
+
+impl Game {
+    /// Whether `player_id` can make `action` right now, as far as they know.
+    pub fn is_legal(&self, player_id: u64, action: &ActionKind) -> bool {
+        self.check_action(player_id, action).is_ok()
+    }
+
+    /// Every action `player_id` can make right now, placements first in row order.
+    pub fn legal_moves(&self, player_id: u64) -> Vec<ActionKind> {
+        let board = &self.shared.board;
+        // Pixel go coordinates go one past the board.
+        let extra = self.shared.mods.pixel as u32;
+        let (width, height) = (board.width + extra, board.height + extra);
+        let places = (0..height).flat_map(|y| (0..width).map(move |x| ActionKind::Place(x, y)));
+        let others = vec![
+            ActionKind::Pass,
+            ActionKind::Cancel,
+            ActionKind::Resign,
+            ActionKind::RequestUndo,
+            ActionKind::AcceptUndo,
+            ActionKind::DeclineUndo,
+        ];
+        places
+            .chain(others)
+            .filter(|action| self.is_legal(player_id, action))
+            .collect()
+    }
+
+    /// The error `make_action` would give, if any.
+    fn check_action(&self, player_id: u64, action: &ActionKind) -> MakeActionResult<()> {
+        let shared = &self.shared;
+        if !shared.seats.iter().any(|s| s.player == Some(player_id)) {
+            return Err(MakeActionError::NotPlayer);
+        }
+
+        match &self.state {
+            GameState::Done(_) => Err(MakeActionError::GameDone),
+            _ if *action == ActionKind::Resign => Ok(()),
+            GameState::FreePlacement(state) => {
+                check_free_placement(state, shared, player_id, action)
+            }
+            GameState::Play(state) => check_play(state, shared, player_id, action),
+            GameState::Scoring(state) => match action {
+                // Toggling a group, empty points don't do anything.
+                ActionKind::Place(x, y) => state
+                    .groups
+                    .iter()
+                    .any(|g| g.points.contains(&(*x, *y)))
+                    .then_some(())
+                    .ok_or(MakeActionError::PointOccupied),
+                ActionKind::RequestUndo => Err(MakeActionError::UndoNotAllowed),
+                ActionKind::AcceptUndo | ActionKind::DeclineUndo => {
+                    Err(MakeActionError::NoUndoRequest)
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+fn check_free_placement(
+    state: &FreePlacement,
+    shared: &SharedState,
+    player_id: u64,
+    action: &ActionKind,
+) -> MakeActionResult<()> {
+    // Only black places handicap stones.
+    let black = shared
+        .seats
+        .iter()
+        .any(|s| s.player == Some(player_id) && s.team == Color(1));
+    if state.handicap && !black {
+        return Err(MakeActionError::NotTurn);
+    }
+
+    match *action {
+        ActionKind::Place(x, y) => {
+            let idx = state
+                .view_index(&shared.seats, player_id)
+                .ok_or(MakeActionError::NotPlayer)?;
+            if state.stones_placed[idx] >= state.placement_count(shared) {
+                return Err(MakeActionError::PointOccupied);
+            }
+            let board = &state.boards[idx];
+            let points = modifiers::footprint(&shared.modifiers, board, (x, y))?;
+            if points.iter().all(|&p| !board.get_point(p).is_empty()) {
+                return Err(MakeActionError::PointOccupied);
+            }
+            Ok(())
+        }
+        ActionKind::Pass if state.handicap => Err(MakeActionError::NotTurn),
+        ActionKind::RequestUndo => Err(MakeActionError::UndoNotAllowed),
+        ActionKind::AcceptUndo | ActionKind::DeclineUndo => Err(MakeActionError::NoUndoRequest),
+        _ => Ok(()),
+    }
+}
+
+fn check_play(
+    state: &PlayState,
+    shared: &SharedState,
+    player_id: u64,
+    action: &ActionKind,
+) -> MakeActionResult<()> {
+    let own_turn = shared.seats[shared.turn].player == Some(player_id);
+    let can_undo = || {
+        if shared.board_history.len() < 2 {
+            Err(MakeActionError::OutOfBounds)
+        } else {
+            Ok(())
+        }
+    };
+
+    match *action {
+        ActionKind::Place(..) | ActionKind::Pass | ActionKind::Cancel if !own_turn => {
+            Err(MakeActionError::NotTurn)
+        }
+        ActionKind::Place(x, y) => check_place(state, shared, (x, y)),
+        ActionKind::Pass | ActionKind::Resign => Ok(()),
+        ActionKind::Cancel if shared.mods.undo != UndoPolicy::Free => {
+            Err(MakeActionError::UndoNotAllowed)
+        }
+        ActionKind::Cancel => can_undo(),
+        ActionKind::RequestUndo => match shared.mods.undo {
+            UndoPolicy::Never => Err(MakeActionError::UndoNotAllowed),
+            UndoPolicy::Free if !own_turn => Err(MakeActionError::NotTurn),
+            UndoPolicy::Free => can_undo(),
+            UndoPolicy::Approval if state.undo_request.is_some() => {
+                Err(MakeActionError::UndoPending)
+            }
+            UndoPolicy::Approval => match last_mover(shared) {
+                None => Err(MakeActionError::OutOfBounds),
+                Some(seat) if shared.seats[seat].player != Some(player_id) => {
+                    Err(MakeActionError::NotTurn)
+                }
+                Some(_) => Ok(()),
+            },
+        },
+        ActionKind::AcceptUndo | ActionKind::DeclineUndo => {
+            let request = state
+                .undo_request
+                .as_ref()
+                .ok_or(MakeActionError::NoUndoRequest)?;
+            let answering = shared
+                .seats
+                .iter()
+                .zip(&request.accepted)
+                .any(|(seat, accepted)| seat.player == Some(player_id) && !accepted);
+            if answering {
+                Ok(())
+            } else {
+                Err(MakeActionError::NotTurn)
+            }
+        }
+    }
+}
+
+/// Plays a stone out on a copy of the board the seat to move knows.
+fn check_place(state: &PlayState, shared: &SharedState, point: Point) -> MakeActionResult<()> {
+    let team = shared.seats[shared.turn].team;
+    let visibility = shared.board_visibility.as_ref();
+    // Hidden stones the seat can see are revealed by playing on them.
+    let known_hidden = |p: Point| visibility.is_some_and(|v| v.get_point(p).get(team.as_usize()));
+
+    let mut board = known_board(shared, team);
+    let points = modifiers::footprint(&shared.modifiers, &board, point)?;
+    let mut placed = points
+        .iter()
+        .copied()
+        .filter(|&p| board.get_point(p).is_empty())
+        .collect::<GroupVec<Point>>();
+    if placed.is_empty() {
+        if points.iter().any(|&p| known_hidden(p)) {
+            return Ok(());
+        }
+        return Err(MakeActionError::PointOccupied);
+    }
+    for &p in &placed {
+        *board.point_mut(p) = team;
+    }
+
+    let mut captures = 0;
+    let chains = Chains::new(&board);
+    let dead = chains
+        .iter()
+        .filter(|c| c.liberties.is_empty() && c.color != team)
+        .flat_map(|c| c.stones.iter().copied())
+        .collect::<Vec<_>>();
+    for &p in &dead {
+        *board.point_mut(p) = Color::empty();
+        captures += 1;
+    }
+    let chains = if dead.is_empty() {
+        chains
+    } else {
+        Chains::new(&board)
+    };
+
+    let mut suicided = false;
+    let mut revealed = false;
+    for chain in chains
+        .iter()
+        .filter(|c| c.liberties.is_empty() && c.color == team)
+    {
+        let suicide = shared.mods.suicide && chain.stones.len() > 1;
+        for &p in &chain.stones {
+            if suicide || placed.contains(&p) {
+                *board.point_mut(p) = Color::empty();
+            }
+            revealed =
+                revealed || known_hidden(p) || board.surrounding_points(p).any(&known_hidden);
+        }
+        if suicide {
+            captures += chain.stones.len();
+            suicided = true;
+        }
+        placed.retain(|p| !chain.stones.contains(p));
+    }
+    if placed.is_empty() && !suicided {
+        if revealed {
+            return Ok(());
+        }
+        return Err(MakeActionError::Suicide);
+    }
+
+    let hidden = visibility.is_some_and(|v| v.points.iter().any(|x| !x.is_empty()));
+    let ko = if hidden {
+        !shared.mods.pixel && state.ko_point == Some(point)
+    } else {
+        let hash = zobrist::hash(&board);
+        repeats_position(shared, hash, shared.visibility_hash, captures)
+    };
+    if ko {
+        return Err(MakeActionError::Ko);
+    }
+    Ok(())
+}
+
+/// The board with the stones hidden from `team` left out.
+fn known_board(shared: &SharedState, team: Color) -> Board {
+    let mut board = shared.board.clone();
+    if let Some(visibility) = &shared.board_visibility {
+        for (point, seen) in board.points.iter_mut().zip(&visibility.points) {
+            if !seen.is_empty() && !seen.get(team.as_usize()) {
+                *point = Color::empty();
+            }
+        }
+    }
+    board
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{ActionKind, Game, GameModifier, HiddenMoveGo, MakeActionError};
+
+    /// `is_legal` agrees with trying every placement on a copy of the game.
+    fn assert_matches_make_action(game: &Game, player_id: u64) {
+        let extra = game.shared.mods.pixel as u32;
+        for y in 0..game.shared.board.height + extra {
+            for x in 0..game.shared.board.width + extra {
+                let action = ActionKind::Place(x, y);
+                let made = game.clone().make_action(player_id, action.clone());
+                assert_eq!(
+                    game.is_legal(player_id, &action),
+                    made.is_ok(),
+                    "{:?}: {:?}",
+                    action,
+                    made
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_make_action() {
+        // Black and white build a ko around (1, 1) and (2, 1), with a two stone black chain
+        // in atari in the corner.
+        let moves = [
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (3, 1),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (0, 4),
+            (3, 4),
+            (1, 4),
+            (4, 3),
+            (1, 1),
+        ];
+        for &(pixel, suicide) in &[(false, false), (false, true), (true, false)] {
+            let mods = GameModifier {
+                pixel,
+                suicide,
+                ..GameModifier::default()
+            };
+            let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (5, 5), mods).unwrap();
+            game.take_seat(1, 0).unwrap();
+            game.take_seat(2, 1).unwrap();
+            for (i, &(x, y)) in moves.iter().enumerate() {
+                let player = 1 + i as u64 % 2;
+                assert_matches_make_action(&game, player);
+                let _ = game.make_action(player, ActionKind::Place(x, y));
+            }
+            assert_matches_make_action(&game, 1);
+        }
+    }
+
+    #[test]
+    fn state_specific_actions() {
+        let mods = GameModifier {
+            hidden_move: Some(HiddenMoveGo {
+                placement_count: 1,
+                teams_share_stones: true,
+            }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (5, 5), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        assert!(!game.is_legal(3, &ActionKind::Pass));
+
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+        assert!(!game.is_legal(1, &ActionKind::Place(3, 3)));
+        // White doesn't know about black's stone.
+        assert!(game.is_legal(2, &ActionKind::Place(2, 2)));
+        assert_eq!(game.legal_moves(2).len(), 25 + 3);
+
+        game.make_action(1, ActionKind::Pass).unwrap();
+        game.make_action(2, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+        assert!(!game.is_legal(2, &ActionKind::Pass));
+        assert!(game.is_legal(1, &ActionKind::Place(2, 2)));
+        assert!(game.is_legal(1, &ActionKind::Place(0, 0)));
+        let moves = game.legal_moves(1);
+        // Nothing to undo yet.
+        assert!(!moves.contains(&ActionKind::Cancel) && !moves.contains(&ActionKind::AcceptUndo));
+        assert!(game.clone().make_action(1, ActionKind::Cancel).is_err());
+
+        game.make_action(1, ActionKind::Resign).unwrap();
+        assert!(game.legal_moves(1).is_empty());
+        assert!(!game.is_legal(2, &ActionKind::Pass));
+        assert_eq!(
+            game.check_action(2, &ActionKind::Pass),
+            Err(MakeActionError::GameDone)
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! The stored replay format.
+//!
+//! Replays are kept in the database for as long as the game exists, so every dump has to
+//! stay loadable. `version` is bumped whenever the layout of `GameReplay` changes, older
+//! dumps are upgraded step by step in `migrate`. Changes to how a rule plays are tracked
+//! separately in `RuleVersions`, which lets old games replay with the rules they were
+//! played under.
+//!
+//! Version history:
+//! 0: No version or rule versions stored.
+//! 1: Added `version` and `rules`.
+//! 2: Added `time` to actions and the `Timeout` action.
+//! 3: Added `result`, the `Resign` action and the `Forfeit` action.
+//! 4: Added the undo request actions.
+
+use serde::{Deserialize, Serialize};
+
+use super::{GameAction, GameModifier, GameResult, GroupVec, RuleVersions};
+
+/// The version written by `Game::dump`.
+pub const FORMAT_VERSION: u32 = 4;
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct GameReplay {
+    pub actions: Vec<GameAction>,
+    pub mods: GameModifier,
+    pub komis: GroupVec<i32>,
+    pub seats: GroupVec<u8>,
+    pub size: (u8, u8),
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub rules: RuleVersions,
+    /// Result of a finished game, as decided when it ended
+    #[serde(default)]
+    pub result: Option<GameResult>,
+}
+
+impl GameReplay {
+    /// Reads a dump of any known version and upgrades it to the current one.
+    pub fn from_slice(dump: &[u8]) -> Option<GameReplay> {
+        let replay: GameReplay = serde_cbor::from_slice(dump).ok()?;
+        replay.migrate()
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut vec = Vec::new();
+        self.serialize(&mut serde_cbor::Serializer::new(&mut vec).packed_format())
+            .expect("Game dump failed");
+        vec
+    }
+
+    fn migrate(mut self) -> Option<GameReplay> {
+        if self.version > FORMAT_VERSION {
+            return None;
+        }
+        if self.version == 0 {
+            // Everything was played with the original rules.
+            self.rules = RuleVersions::ORIGINAL;
+            self.version = 1;
+        }
+        if self.version == 1 {
+            // Actions without a time are all made at 0, games had no clocks.
+            self.version = 2;
+        }
+        if self.version == 2 {
+            // Results get counted again while loading.
+            self.version = 3;
+        }
+        if self.version == 3 {
+            self.version = 4;
+        }
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay(version: u32) -> GameReplay {
+        GameReplay {
+            actions: Vec::new(),
+            mods: GameModifier::default(),
+            komis: tinyvec::tiny_vec![0, 0],
+            seats: tinyvec::tiny_vec![1, 2],
+            size: (9, 9),
+            version,
+            rules: RuleVersions::CURRENT,
+            result: None,
+        }
+    }
+
+    #[test]
+    fn migrates_old_versions_only() {
+        let old = GameReplay::from_slice(&replay(0).to_vec()).unwrap();
+        assert_eq!(
+            (old.version, old.rules),
+            (FORMAT_VERSION, RuleVersions::ORIGINAL)
+        );
+
+        let current = GameReplay::from_slice(&replay(FORMAT_VERSION).to_vec()).unwrap();
+        assert_eq!(current.rules, RuleVersions::CURRENT);
+
+        assert!(GameReplay::from_slice(&replay(FORMAT_VERSION + 1).to_vec()).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+//! Game results.
+//!
+//! A finished game has a result saying who won and why. Results are per team, so every seat
+//! of a rengo team shares one, and multi-color games rank all of their teams.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Color, GroupVec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResultReason {
+    /// Counted after everyone accepted the scoring
+    Score,
+    Resignation,
+    Timeout,
+    Forfeit,
+    /// The winner captured the stones the capture target asks for
+    Captures,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamResult {
+    pub team: Color,
+    /// Score in half points when the game ended, or the stones captured for games won by
+    /// captures
+    pub score: i32,
+    /// 1 for the winner, teams with the same score share a place.
+    pub place: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameResult {
+    /// None for a tie, or when the game has no winners like zen go
+    pub winner: Option<Color>,
+    /// Half points between the winner and the next team, for results by score
+    pub margin: Option<i32>,
+    pub reason: ResultReason,
+    /// Every team, best place first
+    pub teams: Vec<TeamResult>,
+}
+
+impl GameResult {
+    /// Ranks the teams by score.
+    pub fn by_score(scores: &GroupVec<i32>, has_winner: bool) -> GameResult {
+        let teams = rank(scores, None);
+        let margin = match &teams[..] {
+            [first, second, ..] => first.score - second.score,
+            _ => 0,
+        };
+        let winner = match &teams[..] {
+            [first, ..] if has_winner && margin > 0 => Some(first.team),
+            _ => None,
+        };
+        GameResult {
+            winner,
+            margin: Some(margin),
+            reason: ResultReason::Score,
+            teams,
+        }
+    }
+
+    /// `loser` ended the game by resigning, timing out or forfeiting. It places last, the
+    /// other teams are ranked by the score when the game ended.
+    pub fn lost_by(loser: Color, reason: ResultReason, scores: &GroupVec<i32>) -> GameResult {
+        let teams = rank(scores, Some(loser));
+        GameResult {
+            winner: teams.first().map(|x| x.team).filter(|&x| x != loser),
+            margin: None,
+            reason,
+            teams,
+        }
+    }
+
+    /// `winner` reached the capture target. Teams are ranked by the stones they captured.
+    pub fn by_captures(winner: Color, captures: &GroupVec<i32>) -> GameResult {
+        GameResult {
+            winner: Some(winner),
+            margin: None,
+            reason: ResultReason::Captures,
+            teams: rank(captures, None),
+        }
+    }
+}
+
+fn rank(scores: &GroupVec<i32>, loser: Option<Color>) -> Vec<TeamResult> {
+    let mut teams = scores
+        .iter()
+        .enumerate()
+        .map(|(idx, &score)| TeamResult {
+            team: Color(idx as u8 + 1),
+            score,
+            place: 0,
+        })
+        .collect::<Vec<_>>();
+    // Stable, so tied teams stay in color order.
+    teams.sort_by_key(|x| (Some(x.team) == loser, -x.score));
+
+    for idx in 0..teams.len() {
+        let tied =
+            idx > 0 && Some(teams[idx].team) != loser && teams[idx - 1].score == teams[idx].score;
+        teams[idx].place = if tied {
+            teams[idx - 1].place
+        } else {
+            idx as u32 + 1
+        };
+    }
+    teams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn places(result: &GameResult) -> Vec<(u8, u32)> {
+        result.teams.iter().map(|x| (x.team.0, x.place)).collect()
+    }
+
+    #[test]
+    fn ranks_teams() {
+        let result = GameResult::by_score(&tinyvec::tiny_vec![10, 25, 10], true);
+        assert_eq!(result.winner, Some(Color(2)));
+        assert_eq!(result.margin, Some(15));
+        assert_eq!(places(&result), vec![(2, 1), (1, 2), (3, 2)]);
+
+        let tie = GameResult::by_score(&tinyvec::tiny_vec![7, 7], true);
+        assert_eq!((tie.winner, tie.margin), (None, Some(0)));
+
+        let resigned = GameResult::lost_by(
+            Color(2),
+            ResultReason::Resignation,
+            &tinyvec::tiny_vec![10, 25, 12],
+        );
+        assert_eq!(resigned.winner, Some(Color(3)));
+        assert_eq!(places(&resigned), vec![(3, 1), (1, 2), (2, 3)]);
+    }
+}
@@ -0,0 +1,1154 @@
+//! SGF (Smart Game Format, FF[4]) support.
+//!
+//! Whatever standard properties can express is written with them, so other Go tools can
+//! at least show the stones of regular games. Everything variant specific goes to custom
+//! `VG` properties:
+//!
+//! * `VGSEATS` - team of every seat, in seat order
+//! * `VGKOMI` - komi of every team in half points
+//! * `VGMODS` - the `GameModifier` as hex encoded CBOR
+//! * `VGADD[c:xy]` - setup stone of team `c` for colors that `AB`/`AW` can't express
+//! * `VGHIDDEN[s:xy]` - hidden move placement made by seat `s`
+//! * `VGTURN` - the seat that moves first after setup
+//! * `VGPLAY[c:xy]` / `VGPLAY[c]` - move or pass by team `c` that `B`/`W` can't express,
+//!   i.e. a third or fourth color or a pixel blob (`xy` is the action coordinate)
+//! * `VGREVEAL[c:xy]` - a move by team `c` that only revealed hidden stones
+//! * `VGTERRITORY[c:xy]` - territory of team `c` for colors `TB`/`TW` can't express
+//! * `VGSCORES` - final score of every team in half points
+//! * `VGRESIGN[c]` - team `c` resigned
+
+use super::*;
+use crate::states::ScoringState;
+
+///////////////////////////////////////////////////////////////////////////////
+//                                    Data                                   //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Node {
+    props: Vec<(String, Vec<String>)>,
+}
+
+impl Node {
+    fn push(&mut self, ident: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.props.iter_mut().find(|(i, _)| i == ident) {
+            Some((_, values)) => values.push(value),
+            None => self.props.push((ident.to_owned(), vec![value])),
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push(';');
+        for (ident, values) in &self.props {
+            out.push_str(ident);
+            for value in values {
+                out.push('[');
+                for c in value.chars() {
+                    if c == ']' || c == '\\' {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+                out.push(']');
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                   Export                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+pub(super) fn export(game: &Game, nick: impl Fn(u64) -> Option<String>) -> String {
+    let mut replay = game.restarted().expect("Game setup should be valid");
+    let shared = &game.shared;
+
+    let mut root = Node::default();
+    root.push("FF", "4");
+    root.push("GM", "1");
+    root.push("CA", "UTF-8");
+    root.push(
+        "AP",
+        concat!("variant-go-server:", env!("CARGO_PKG_VERSION")),
+    );
+    if shared.board.width == shared.board.height {
+        root.push("SZ", shared.board.width.to_string());
+    } else {
+        root.push(
+            "SZ",
+            format!("{}:{}", shared.board.width, shared.board.height),
+        );
+    }
+    root.push(
+        "RU",
+        match shared.mods.scoring {
+            ScoringRules::Area => "Chinese",
+            ScoringRules::Territory => "Japanese",
+        },
+    );
+    if shared.komis.len() >= 2 {
+        root.push("KM", format_half_points(shared.komis[1] - shared.komis[0]));
+    }
+    if let Some(handicap) = &shared.mods.handicap {
+        root.push("HA", handicap.stones.to_string());
+    }
+    for seat in &replay.shared.seats {
+        root.push("VGSEATS", seat.team.0.to_string());
+    }
+    for komi in &shared.komis {
+        root.push("VGKOMI", komi.to_string());
+    }
+    let mods = serde_cbor::to_vec(&shared.mods).expect("Modifier serialization failed");
+    root.push("VGMODS", to_hex(&mods));
+
+    // Fixed handicap stones are on the board from the start.
+    let mut setup = if replay.shared.board.points.iter().any(|c| !c.is_empty()) {
+        let mut node = setup_node(&replay.shared.board, &[]);
+        node.push("PL", "W");
+        Some(node)
+    } else {
+        None
+    };
+    let mut hidden: Vec<(usize, (u32, u32))> = Vec::new();
+    let mut players: Vec<(Color, u64)> = Vec::new();
+    // Every move node is tagged with the history length after it so undos can drop it.
+    let mut moves: Vec<(usize, Node)> = Vec::new();
+
+    for action in &game.actions {
+        let user_id = action.user_id;
+        let kind = match &action.action {
+            ReplayActionKind::TakeSeat(seat_id) => {
+                if let Some(seat) = replay.shared.seats.get(*seat_id as usize) {
+                    players.push((seat.team, user_id));
+                }
+                let _ = replay.take_seat(user_id, *seat_id as _);
+                continue;
+            }
+            ReplayActionKind::LeaveSeat(seat_id) => {
+                let _ = replay.leave_seat(user_id, *seat_id as _);
+                continue;
+            }
+            ReplayActionKind::Setup(stones) => {
+                if replay.setup(stones.clone()).is_ok() {
+                    let mut node = setup_node(&replay.shared.board, &[]);
+                    let team = replay.shared.seats[replay.shared.turn].team;
+                    if team.0 <= 2 {
+                        node.push("PL", color_ident(team));
+                    }
+                    node.push("VGTURN", replay.shared.turn.to_string());
+                    setup = Some(node);
+                }
+                continue;
+            }
+            ReplayActionKind::Timeout
+            | ReplayActionKind::Forfeit(_)
+            | ReplayActionKind::StartClock => continue,
+            ReplayActionKind::Play(kind) => kind,
+        };
+
+        let team = replay.shared.seats[replay.shared.turn].team;
+        let own_seat = replay
+            .shared
+            .seats
+            .iter()
+            .position(|s| s.player == Some(user_id));
+        let history_len = replay.shared.board_history.len();
+        let was_placement = matches!(replay.state, GameState::FreePlacement(_));
+        let was_play = matches!(replay.state, GameState::Play(_));
+
+        if replay.make_action(user_id, kind.clone()).is_err() {
+            continue;
+        }
+
+        if was_placement {
+            let seat_idx = own_seat.expect("Placement made without a seat");
+            match kind {
+                ActionKind::Place(x, y) if shared.mods.hidden_move.is_some() => {
+                    hidden.push((seat_idx, (*x, *y)))
+                }
+                ActionKind::Cancel => {
+                    let seats = &replay.shared.seats;
+                    let shares = shared
+                        .mods
+                        .hidden_move
+                        .as_ref()
+                        .is_some_and(|x| x.teams_share_stones);
+                    hidden.retain(|&(s, _)| {
+                        if shares {
+                            seats[s].team != seats[seat_idx].team
+                        } else {
+                            s != seat_idx
+                        }
+                    });
+                }
+                _ => {}
+            }
+
+            if let GameState::Play(_) = replay.state {
+                setup = Some(setup_node(&replay.shared.board, &hidden));
+            }
+            continue;
+        }
+
+        if !was_play {
+            continue;
+        }
+
+        let new_len = replay.shared.board_history.len();
+        match kind {
+            ActionKind::Place(x, y) => {
+                let mut node = Node::default();
+                if new_len > history_len {
+                    let standard = !shared.mods.pixel && team.0 <= 2;
+                    if standard {
+                        node.push(color_ident(team), point_to_sgf((*x, *y)));
+                    } else {
+                        node.push("VGPLAY", format!("{}:{}", team.0, point_to_sgf((*x, *y))));
+                    }
+                } else {
+                    node.push("VGREVEAL", format!("{}:{}", team.0, point_to_sgf((*x, *y))));
+                }
+                moves.push((new_len, node));
+            }
+            ActionKind::Pass => {
+                let mut node = Node::default();
+                if team.0 <= 2 {
+                    node.push(color_ident(team), "");
+                } else {
+                    node.push("VGPLAY", team.0.to_string());
+                }
+                moves.push((new_len, node));
+            }
+            // Undo requests only take moves back once accepted.
+            ActionKind::Cancel | ActionKind::RequestUndo | ActionKind::AcceptUndo => {
+                while moves.last().is_some_and(|(len, _)| *len > new_len) {
+                    moves.pop();
+                }
+            }
+            ActionKind::DeclineUndo | ActionKind::Resign => {}
+        }
+    }
+
+    for color in 1..=2 {
+        let mut names = Vec::new();
+        for (_, user_id) in players.iter().filter(|(team, _)| team.0 == color) {
+            let name = nick(*user_id).unwrap_or_else(|| format!("Player {}", user_id));
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        if !names.is_empty() {
+            let ident = if color == 1 { "PB" } else { "PW" };
+            root.push(ident, names.join(", "));
+        }
+    }
+
+    let mut nodes = vec![root];
+    nodes.extend(setup);
+    nodes.extend(moves.into_iter().map(|(_, node)| node));
+
+    let counted = shared
+        .result
+        .as_ref()
+        .is_none_or(|x| x.reason == ResultReason::Score);
+    match &game.state {
+        GameState::Scoring(scoring) | GameState::Done(scoring) if counted => {
+            let node = nodes.last_mut().expect("SGF has a root node");
+            write_scoring(node, &shared.board, scoring);
+            if let GameState::Done(_) = game.state {
+                for score in &scoring.scores {
+                    nodes[0].push("VGSCORES", score.to_string());
+                }
+            }
+        }
+        _ => {}
+    }
+    if let Some(result) = &shared.result {
+        if shared.komis.len() == 2 {
+            nodes[0].push("RE", format_result(result));
+        }
+        if result.reason == ResultReason::Resignation {
+            let loser = result.teams.last().expect("Result without teams").team;
+            nodes[0].push("VGRESIGN", loser.0.to_string());
+        }
+    }
+
+    let mut out = String::from("(");
+    for node in &nodes {
+        node.write(&mut out);
+        out.push('\n');
+    }
+    out.push(')');
+    out
+}
+
+fn setup_node(board: &Board, hidden: &[(usize, (u32, u32))]) -> Node {
+    let mut node = Node::default();
+    for (idx, color) in board.points.iter().enumerate() {
+        if color.is_empty() {
+            continue;
+        }
+        let point = board.idx_to_coord(idx).expect("Index within board");
+        match color.0 {
+            1 => node.push("AB", point_to_sgf(point)),
+            2 => node.push("AW", point_to_sgf(point)),
+            c => node.push("VGADD", format!("{}:{}", c, point_to_sgf(point))),
+        }
+    }
+    for (seat, point) in hidden {
+        node.push("VGHIDDEN", format!("{}:{}", seat, point_to_sgf(*point)));
+    }
+    node
+}
+
+fn write_scoring(node: &mut Node, board: &Board, scoring: &ScoringState) {
+    for (idx, owner) in scoring.points.points.iter().enumerate() {
+        // Stones of the owner itself are not territory.
+        if owner.is_empty() || board.points[idx] == *owner {
+            continue;
+        }
+        let point = board.idx_to_coord(idx).expect("Index within board");
+        match owner.0 {
+            1 => node.push("TB", point_to_sgf(point)),
+            2 => node.push("TW", point_to_sgf(point)),
+            c => node.push("VGTERRITORY", format!("{}:{}", c, point_to_sgf(point))),
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                   Import                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SgfError {
+    /// The file is not valid SGF. Contains the byte offset of the error.
+    Syntax(usize),
+    /// The file is for some other game than Go.
+    NotGo,
+    /// A property value could not be understood.
+    InvalidValue(String),
+    /// The game setup was not accepted by `Game::standard`.
+    Rules(RulesError),
+    /// The file uses something the engine has no way to represent.
+    Unsupported(&'static str),
+    /// A move was out of turn, e.g. the same color moving twice in a row.
+    OutOfTurn { move_number: usize },
+    /// A move was rejected by the rules.
+    IllegalMove {
+        move_number: usize,
+        error: MakeActionError,
+    },
+}
+
+pub(super) fn import(sgf: &str) -> Result<Game, SgfError> {
+    let nodes = Parser::new(sgf).collection()?;
+    let root = nodes.first().ok_or(SgfError::Syntax(0))?;
+
+    if let Some(gm) = root.get("GM") {
+        if gm != "1" {
+            return Err(SgfError::NotGo);
+        }
+    }
+
+    let size = match root.get("SZ") {
+        Some(sz) => {
+            let mut parts = sz.splitn(2, ':');
+            let width = parse_value::<u8>("SZ", parts.next().unwrap_or(""))?;
+            let height = match parts.next() {
+                Some(h) => parse_value::<u8>("SZ", h)?,
+                None => width,
+            };
+            (width, height)
+        }
+        None => (19, 19),
+    };
+
+    let seats = match root.get_all("VGSEATS") {
+        [] => vec![1, 2],
+        seats => seats
+            .iter()
+            .map(|x| parse_value::<u8>("VGSEATS", x))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let komis = match root.get_all("VGKOMI") {
+        [] => {
+            let mut komis: GroupVec<i32> = tinyvec::tiny_vec![0, 0];
+            if let Some(km) = root.get("KM") {
+                let half_points = parse_value::<f32>("KM", km)? * 2.0;
+                if half_points.fract() != 0.0 {
+                    return Err(SgfError::Unsupported("komi must be a multiple of 0.5"));
+                }
+                komis[1] = half_points as i32;
+            }
+            komis
+        }
+        komis => komis
+            .iter()
+            .map(|x| parse_value::<i32>("VGKOMI", x))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mods = match root.get("VGMODS") {
+        Some(hex) => from_hex(hex)
+            .and_then(|bytes| serde_cbor::from_slice(&bytes).ok())
+            .ok_or_else(|| SgfError::InvalidValue("VGMODS".to_owned()))?,
+        None => GameModifier {
+            scoring: match root.get("RU") {
+                Some(ru) if ru.eq_ignore_ascii_case("japanese") => ScoringRules::Territory,
+                Some(ru) if ru.eq_ignore_ascii_case("korean") => ScoringRules::Territory,
+                _ => ScoringRules::Area,
+            },
+            ..GameModifier::default()
+        },
+    };
+
+    let mut game = Game::standard(&seats, komis, size, mods).map_err(SgfError::Rules)?;
+
+    // Every seat gets a stand-in player for the replay. They leave once we're done so
+    // the seats can be taken in a room.
+    for seat_idx in 0..seats.len() {
+        game.take_seat(seat_idx as u64, seat_idx)
+            .expect("Seats of a new game are open");
+    }
+
+    let mut setup = Setup {
+        stones: Vec::new(),
+        turn: 0,
+    };
+    let mut turn_set = false;
+    let mut hidden = Vec::new();
+    let mut move_number = 0;
+    let mut moves_started = false;
+
+    for node in &nodes {
+        let has_setup = ["AB", "AW", "AE", "VGADD", "VGHIDDEN", "PL", "VGTURN"]
+            .iter()
+            .any(|ident| node.get(ident).is_some());
+        if has_setup && moves_started {
+            return Err(SgfError::Unsupported("setup after the first move"));
+        }
+        if node.get("AE").is_some() {
+            return Err(SgfError::Unsupported("removing stones with AE"));
+        }
+
+        for (ident, color) in &[("AB", Color(1)), ("AW", Color(2))] {
+            for value in node.get_all(ident) {
+                for point in parse_point_list(ident, value)? {
+                    setup.stones.push((point, *color));
+                }
+            }
+        }
+        for value in node.get_all("VGADD") {
+            let (color, point) = parse_colored_point("VGADD", value)?;
+            setup
+                .stones
+                .push((point.ok_or_else(|| invalid("VGADD"))?, color));
+        }
+        for value in node.get_all("VGHIDDEN") {
+            let (seat, point) = parse_colored_point("VGHIDDEN", value)?;
+            hidden.push((seat.0 as u64, point.ok_or_else(|| invalid("VGHIDDEN"))?));
+        }
+        if let Some(pl) = node.get("PL") {
+            let color = parse_color_ident(pl).ok_or_else(|| invalid("PL"))?;
+            setup.turn = first_seat_of(&game, color).ok_or_else(|| invalid("PL"))?;
+            turn_set = true;
+        }
+        if let Some(turn) = node.get("VGTURN") {
+            setup.turn = parse_value("VGTURN", turn)?;
+            turn_set = true;
+        }
+
+        let action = match node_action(node, &game.shared.board)? {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if !moves_started {
+            moves_started = true;
+            apply_setup(&mut game, &mut setup, &hidden, turn_set, Some(action.0))?;
+        }
+
+        let (color, kind) = action;
+        let seat = game.shared.turn;
+        if game.shared.seats[seat].team != color {
+            return Err(SgfError::OutOfTurn { move_number });
+        }
+        game.make_action(seat as u64, kind)
+            .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+        move_number += 1;
+    }
+
+    // Positions and problems have no moves at all.
+    if !moves_started {
+        apply_setup(&mut game, &mut setup, &hidden, turn_set, None)?;
+    }
+
+    if let Some(loser) = resigned_team(root)? {
+        let seat = first_seat_of(&game, loser).ok_or_else(|| invalid("RE"))?;
+        game.make_action(seat as u64, ActionKind::Resign)
+            .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+    } else if let GameState::Scoring(_) = game.state {
+        apply_scoring(&mut game, nodes.last().expect("SGF has a root node"))?;
+        if root.get("RE").is_some() || root.get("VGSCORES").is_some() {
+            for seat in 0..seats.len() {
+                game.make_action(seat as u64, ActionKind::Pass)
+                    .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+            }
+        }
+    }
+
+    for seat_idx in 0..seats.len() {
+        let _ = game.leave_seat(seat_idx as u64, seat_idx);
+    }
+
+    Ok(game)
+}
+
+/// Puts the setup of the file on the board, before the first move of `first_color` if
+/// there is one.
+fn apply_setup(
+    game: &mut Game,
+    setup: &mut Setup,
+    hidden: &[(u64, Point)],
+    turn_set: bool,
+    first_color: Option<Color>,
+) -> Result<(), SgfError> {
+    let move_number = 0;
+    if game.shared.mods.hidden_move.is_some() {
+        // The setup stones are the result of the placements, so we only replay those.
+        for &(seat, (x, y)) in hidden {
+            game.make_action(seat, ActionKind::Place(x, y))
+                .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+        }
+        for seat in 0..game.shared.seats.len() {
+            game.make_action(seat as u64, ActionKind::Pass)
+                .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+        }
+    } else if let GameState::FreePlacement(_) = game.state {
+        // Free handicap stones are placed by black, play starts after the last one.
+        let black = first_seat_of(game, Color(1)).ok_or_else(|| invalid("AB"))?;
+        for &((x, y), _) in &setup.stones {
+            game.make_action(black as u64, ActionKind::Place(x, y))
+                .map_err(|error| SgfError::IllegalMove { move_number, error })?;
+        }
+    } else {
+        // Files from other servers often leave out PL for handicap games.
+        if let Some(color) = first_color {
+            if !turn_set && color != game.shared.seats[setup.turn as usize].team {
+                setup.turn =
+                    first_seat_of(game, color).ok_or(SgfError::OutOfTurn { move_number })?;
+            }
+        }
+        if !setup.stones.is_empty() || setup.turn != 0 {
+            game.setup(setup.clone()).map_err(|_| invalid("AB/AW"))?;
+        }
+    }
+    Ok(())
+}
+
+/// The team that resigned, from `VGRESIGN` or a `RE` like `W+R`.
+fn resigned_team(root: &Node) -> Result<Option<Color>, SgfError> {
+    if let Some(value) = root.get("VGRESIGN") {
+        return parse_value("VGRESIGN", value).map(|c| Some(Color(c)));
+    }
+    let winner = match root.get("RE").and_then(|x| x.split_once('+')) {
+        Some((winner, "R" | "Resign")) => winner,
+        _ => return Ok(None),
+    };
+    match winner {
+        "B" => Ok(Some(Color(2))),
+        "W" => Ok(Some(Color(1))),
+        _ => Err(invalid("RE")),
+    }
+}
+
+/// Reads the move of a node, if it has one.
+fn node_action(node: &Node, board: &Board) -> Result<Option<(Color, ActionKind)>, SgfError> {
+    // "tt" is the traditional pass on boards up to 19x19.
+    let tt_is_pass = board.width <= 19 && board.height <= 19;
+    for (ident, color) in &[("B", Color(1)), ("W", Color(2))] {
+        if let Some(value) = node.get(ident) {
+            let kind = match parse_point(value) {
+                _ if value.is_empty() || (tt_is_pass && value == "tt") => ActionKind::Pass,
+                Some((x, y)) => ActionKind::Place(x, y),
+                None => return Err(invalid(ident)),
+            };
+            return Ok(Some((*color, kind)));
+        }
+    }
+    for ident in &["VGPLAY", "VGREVEAL"] {
+        if let Some(value) = node.get(ident) {
+            let (color, point) = parse_colored_point(ident, value)?;
+            let kind = match point {
+                Some((x, y)) => ActionKind::Place(x, y),
+                None if *ident == "VGPLAY" => ActionKind::Pass,
+                None => return Err(invalid(ident)),
+            };
+            return Ok(Some((color, kind)));
+        }
+    }
+    Ok(None)
+}
+
+/// Marks every group standing in someone else's territory as dead and the rest alive.
+fn apply_scoring(game: &mut Game, node: &Node) -> Result<(), SgfError> {
+    let mut owners = Board::<Color>::empty(game.shared.board.width, game.shared.board.height);
+    for (ident, color) in &[("TB", Color(1)), ("TW", Color(2))] {
+        for value in node.get_all(ident) {
+            for point in parse_point_list(ident, value)? {
+                if owners.point_within(point) {
+                    *owners.point_mut(point) = *color;
+                }
+            }
+        }
+    }
+    for value in node.get_all("VGTERRITORY") {
+        let (color, point) = parse_colored_point("VGTERRITORY", value)?;
+        let point = point.ok_or_else(|| invalid("VGTERRITORY"))?;
+        if owners.point_within(point) {
+            *owners.point_mut(point) = color;
+        }
+    }
+
+    // Without territory markers we go with the estimate made when scoring started.
+    if owners.points.iter().all(|c| c.is_empty()) {
+        return Ok(());
+    }
+
+    let toggled = game
+        .state
+        .assume::<ScoringState>()
+        .groups
+        .iter()
+        .filter(|g| {
+            let owner = owners.get_point(g.points[0]);
+            let dead = !owner.is_empty() && owner != g.team;
+            g.alive == dead
+        })
+        .map(|g| g.points[0])
+        .collect::<Vec<_>>();
+
+    for (x, y) in toggled {
+        game.make_action(0, ActionKind::Place(x, y))
+            .map_err(|error| SgfError::IllegalMove {
+                move_number: game.shared.board_history.len() - 1,
+                error,
+            })?;
+    }
+    Ok(())
+}
+
+fn first_seat_of(game: &Game, color: Color) -> Option<u32> {
+    game.shared
+        .seats
+        .iter()
+        .position(|s| s.team == color)
+        .map(|x| x as u32)
+}
+
+fn invalid(ident: &str) -> SgfError {
+    SgfError::InvalidValue(ident.to_owned())
+}
+
+fn parse_value<T: std::str::FromStr>(ident: &str, value: &str) -> Result<T, SgfError> {
+    value.trim().parse().map_err(|_| invalid(ident))
+}
+
+fn parse_color_ident(value: &str) -> Option<Color> {
+    match value {
+        "B" => Some(Color(1)),
+        "W" => Some(Color(2)),
+        _ => None,
+    }
+}
+
+fn parse_point(value: &str) -> Option<Point> {
+    fn number(c: u8) -> Option<u32> {
+        match c {
+            b'a'..=b'z' => Some((c - b'a') as u32),
+            b'A'..=b'Z' => Some((c - b'A') as u32 + 26),
+            _ => None,
+        }
+    }
+    match value.as_bytes() {
+        &[x, y] => Some((number(x)?, number(y)?)),
+        _ => None,
+    }
+}
+
+/// Parses a point or a compressed rectangle of points like `aa:cc`.
+fn parse_point_list(ident: &str, value: &str) -> Result<Vec<Point>, SgfError> {
+    let mut parts = value.splitn(2, ':');
+    let from = parse_point(parts.next().unwrap_or("")).ok_or_else(|| invalid(ident))?;
+    let to = match parts.next() {
+        Some(p) => parse_point(p).ok_or_else(|| invalid(ident))?,
+        None => from,
+    };
+    let mut points = Vec::new();
+    for y in from.1.min(to.1)..=from.1.max(to.1) {
+        for x in from.0.min(to.0)..=from.0.max(to.0) {
+            points.push((x, y));
+        }
+    }
+    Ok(points)
+}
+
+/// Parses `n:xy` or a bare `n`.
+fn parse_colored_point(ident: &str, value: &str) -> Result<(Color, Option<Point>), SgfError> {
+    let mut parts = value.splitn(2, ':');
+    let color = Color(parse_value(ident, parts.next().unwrap_or(""))?);
+    let point = match parts.next() {
+        Some(p) => Some(parse_point(p).ok_or_else(|| invalid(ident))?),
+        None => None,
+    };
+    Ok((color, point))
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// Parser /////////////////////////////////////////////////////////////////////
+
+impl Node {
+    fn get(&self, ident: &str) -> Option<&str> {
+        self.get_all(ident).first().map(|x| &**x)
+    }
+
+    fn get_all(&self, ident: &str) -> &[String] {
+        self.props
+            .iter()
+            .find(|(i, _)| i == ident)
+            .map_or(&[], |(_, values)| &values[..])
+    }
+}
+
+/// How deep game trees can nest before the file is refused, so the recursion can't run out
+/// of stack.
+const MAX_TREE_DEPTH: usize = 1000;
+
+/// A small recursive descent parser. Only the main line of the game tree is kept.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    /// Game trees open around the current position
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser {
+            input: input.as_bytes(),
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        while let Some(c) = self.input.get(self.pos) {
+            if !c.is_ascii_whitespace() {
+                return Some(*c);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), SgfError> {
+        if self.peek() != Some(c) {
+            return Err(SgfError::Syntax(self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Reads the first game tree of the collection.
+    fn collection(&mut self) -> Result<Vec<Node>, SgfError> {
+        // Anything before the first tree is ignored, like the spec says.
+        while self.pos < self.input.len() && self.input[self.pos] != b'(' {
+            self.pos += 1;
+        }
+        let mut nodes = Vec::new();
+        self.game_tree(&mut nodes)?;
+        Ok(nodes)
+    }
+
+    fn game_tree(&mut self, nodes: &mut Vec<Node>) -> Result<(), SgfError> {
+        if self.depth >= MAX_TREE_DEPTH {
+            return Err(SgfError::Syntax(self.pos));
+        }
+        self.expect(b'(')?;
+        self.depth += 1;
+        while self.peek() == Some(b';') {
+            self.pos += 1;
+            nodes.push(self.node()?);
+        }
+
+        let mut main_line = true;
+        loop {
+            match self.peek() {
+                Some(b'(') if main_line => {
+                    self.game_tree(nodes)?;
+                    main_line = false;
+                }
+                Some(b'(') => self.game_tree(&mut Vec::new())?,
+                Some(b')') => {
+                    self.pos += 1;
+                    self.depth -= 1;
+                    return Ok(());
+                }
+                _ => return Err(SgfError::Syntax(self.pos)),
+            }
+        }
+    }
+
+    fn node(&mut self) -> Result<Node, SgfError> {
+        let mut node = Node::default();
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_alphabetic() {
+                break;
+            }
+            let mut ident = String::new();
+            while let Some(&c) = self.input.get(self.pos) {
+                if !c.is_ascii_alphabetic() {
+                    break;
+                }
+                // Old FF[3] files may use lowercase letters, which are to be ignored.
+                if c.is_ascii_uppercase() {
+                    ident.push(c as char);
+                }
+                self.pos += 1;
+            }
+            if self.peek() != Some(b'[') {
+                return Err(SgfError::Syntax(self.pos));
+            }
+            while self.peek() == Some(b'[') {
+                let value = self.value()?;
+                node.push(&ident, value);
+            }
+        }
+        Ok(node)
+    }
+
+    fn value(&mut self) -> Result<String, SgfError> {
+        self.expect(b'[')?;
+        let mut value = Vec::new();
+        loop {
+            match self.input.get(self.pos) {
+                Some(b']') => break,
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.input.get(self.pos) {
+                        // Escaped newlines are soft line breaks
+                        Some(b'\n') => {}
+                        Some(&c) => value.push(c),
+                        None => return Err(SgfError::Syntax(self.pos)),
+                    }
+                }
+                Some(&c) => value.push(c),
+                None => return Err(SgfError::Syntax(self.pos)),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        String::from_utf8(value).map_err(|_| SgfError::Syntax(self.pos))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//                                  Helpers                                  //
+///////////////////////////////////////////////////////////////////////////////
+
+fn color_ident(color: Color) -> &'static str {
+    match color.0 {
+        1 => "B",
+        2 => "W",
+        _ => unreachable!("Only black and white have standard SGF properties"),
+    }
+}
+
+/// SGF coordinates go a-z and then A-Z, which covers boards up to 52x52.
+fn point_to_sgf((x, y): Point) -> String {
+    fn letter(v: u32) -> char {
+        match v {
+            0..=25 => (b'a' + v as u8) as char,
+            26..=51 => (b'A' + (v - 26) as u8) as char,
+            _ => panic!("Coordinate {} does not fit in SGF", v),
+        }
+    }
+    let mut s = String::with_capacity(2);
+    s.push(letter(x));
+    s.push(letter(y));
+    s
+}
+
+fn format_half_points(value: i32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.abs();
+    if value % 2 == 0 {
+        format!("{}{}", sign, value / 2)
+    } else {
+        format!("{}{}.5", sign, value / 2)
+    }
+}
+
+fn format_result(result: &GameResult) -> String {
+    let winner = match result.winner {
+        Some(winner) => color_ident(winner),
+        None => return "0".to_owned(),
+    };
+    let how = match result.reason {
+        ResultReason::Score => format_half_points(result.margin.unwrap_or(0)),
+        ResultReason::Resignation => "R".to_owned(),
+        ResultReason::Timeout => "T".to_owned(),
+        ResultReason::Forfeit => "F".to_owned(),
+        ResultReason::Captures => String::new(),
+    };
+    format!("{}+{}", winner, how)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_player_game() -> Game {
+        let mut game = Game::standard(
+            &[1, 2],
+            tinyvec::tiny_vec![0, 15],
+            (9, 9),
+            Default::default(),
+        )
+        .unwrap();
+        game.take_seat(10, 0).unwrap();
+        game.take_seat(20, 1).unwrap();
+        game
+    }
+
+    #[test]
+    fn export_moves_and_undo() {
+        let mut game = two_player_game();
+        game.make_action(10, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(20, ActionKind::Place(6, 6)).unwrap();
+        game.make_action(10, ActionKind::Place(4, 4)).unwrap();
+        game.make_action(10, ActionKind::Cancel).unwrap_err();
+        game.make_action(20, ActionKind::Cancel).unwrap();
+        game.make_action(10, ActionKind::Pass).unwrap();
+
+        let sgf = game.to_sgf(|id| Some(format!("user{}", id)));
+        assert!(sgf.starts_with("(;FF[4]GM[1]"));
+        assert!(sgf.contains("SZ[9]RU[Chinese]KM[7.5]"));
+        assert!(sgf.contains("PB[user10]PW[user20]"));
+        assert!(sgf.contains(";B[cc]\n;W[gg]\n;B[]\n)"));
+        assert!(!sgf.contains("ee"));
+    }
+
+    #[test]
+    fn export_multi_color_and_pixel() {
+        let mods = GameModifier {
+            pixel: true,
+            ..Default::default()
+        };
+        let mut game =
+            Game::standard(&[1, 2, 3], tinyvec::tiny_vec![0, 0, 0], (9, 9), mods).unwrap();
+        for seat in 0..3 {
+            game.take_seat(seat, seat as usize).unwrap();
+        }
+        game.make_action(0, ActionKind::Place(1, 1)).unwrap();
+        game.make_action(1, ActionKind::Place(5, 5)).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+
+        let sgf = game.to_sgf(|_| None);
+        assert!(sgf.contains("VGSEATS[1][2][3]VGKOMI[0][0][0]"));
+        assert!(sgf.contains(";VGPLAY[1:bb]\n;VGPLAY[2:ff]\n;VGPLAY[3]\n"));
+        assert!(sgf.contains("PB[Player 0]PW[Player 1]"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = two_player_game();
+        game.make_action(10, ActionKind::Place(0, 1)).unwrap();
+        game.make_action(20, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(10, ActionKind::Place(1, 0)).unwrap();
+        game.make_action(20, ActionKind::Pass).unwrap();
+        game.make_action(10, ActionKind::Pass).unwrap();
+
+        let imported = Game::from_sgf(&game.to_sgf(|_| None)).unwrap();
+        assert_eq!(imported.shared.board, game.shared.board);
+        assert_eq!(imported.shared.points, game.shared.points);
+        assert_eq!(imported.shared.komis, game.shared.komis);
+        assert!(matches!(imported.state, GameState::Scoring(_)));
+        assert!(imported.shared.seats.iter().all(|s| s.player.is_none()));
+    }
+
+    #[test]
+    fn round_trip_resignation() {
+        let mut game = two_player_game();
+        game.make_action(10, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(10, ActionKind::Resign).unwrap();
+
+        let sgf = game.to_sgf(|_| None);
+        assert!(sgf.contains("RE[W+R]VGRESIGN[1]"));
+        assert!(!sgf.contains("TB"));
+        let imported = Game::from_sgf(&sgf).unwrap();
+        assert_eq!(imported.shared.result, game.shared.result);
+    }
+
+    #[test]
+    fn round_trip_hidden_move() {
+        let mods = GameModifier {
+            hidden_move: Some(HiddenMoveGo {
+                placement_count: 2,
+                teams_share_stones: false,
+            }),
+            ..Default::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 15], (9, 9), mods).unwrap();
+        game.take_seat(10, 0).unwrap();
+        game.take_seat(20, 1).unwrap();
+        game.make_action(10, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(20, ActionKind::Place(6, 6)).unwrap();
+        game.make_action(10, ActionKind::Pass).unwrap();
+        game.make_action(20, ActionKind::Pass).unwrap();
+        game.make_action(10, ActionKind::Place(4, 4)).unwrap();
+        game.make_action(20, ActionKind::Place(2, 2)).unwrap();
+
+        let sgf = game.to_sgf(|_| None);
+        assert!(sgf.contains("VGHIDDEN[0:cc][1:gg]"));
+        assert!(sgf.contains("VGREVEAL[2:cc]"));
+
+        let imported = Game::from_sgf(&sgf).unwrap();
+        assert_eq!(imported.shared.board, game.shared.board);
+        assert_eq!(
+            imported.shared.board_visibility,
+            game.shared.board_visibility
+        );
+        assert_eq!(imported.shared.turn, game.shared.turn);
+    }
+
+    #[test]
+    fn round_trip_handicap() {
+        for &free_placement in &[false, true] {
+            let mods = GameModifier {
+                handicap: Some(Handicap {
+                    stones: 2,
+                    free_placement,
+                }),
+                ..Default::default()
+            };
+            let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+            game.take_seat(10, 0).unwrap();
+            game.take_seat(20, 1).unwrap();
+            if free_placement {
+                game.make_action(10, ActionKind::Place(4, 4)).unwrap();
+                game.make_action(10, ActionKind::Place(3, 3)).unwrap();
+            }
+            game.make_action(20, ActionKind::Place(5, 5)).unwrap();
+
+            let sgf = game.to_sgf(|_| None);
+            assert!(sgf.contains("HA[2]"));
+            let imported = Game::from_sgf(&sgf).unwrap();
+            assert_eq!(imported.shared.board, game.shared.board);
+            assert_eq!(imported.shared.turn, 0);
+        }
+    }
+
+    #[test]
+    fn import_handicap_game() {
+        let sgf =
+            "(;GM[1]FF[4]SZ[9]KM[0.5]HA[2]RU[Japanese]AB[cc][gg]\n;W[cg];B[gc]\n(;W[ee])(;W[dd]))";
+        let game = Game::from_sgf(sgf).unwrap();
+        let board = &game.shared.board;
+        assert_eq!(board.get_point((2, 2)), Color(1));
+        assert_eq!(board.get_point((2, 6)), Color(2));
+        assert_eq!(board.get_point((4, 4)), Color(2));
+        assert_eq!(board.get_point((3, 3)), Color(0));
+        assert_eq!(game.shared.komis[1], 1);
+        assert_eq!(game.shared.mods.scoring, ScoringRules::Territory);
+
+        // Setup is part of the replay as well.
+        let loaded = Game::load(&game.dump()).unwrap();
+        assert_eq!(&loaded.shared.board, board);
+    }
+
+    #[test]
+    fn import_setup_only_position() {
+        let game = Game::from_sgf("(;GM[1]FF[4]SZ[9]AB[cc][dd]AW[cd]PL[W])").unwrap();
+        let board = &game.shared.board;
+        assert_eq!(board.get_point((2, 2)), Color(1));
+        assert_eq!(board.get_point((3, 3)), Color(1));
+        assert_eq!(board.get_point((2, 3)), Color(2));
+        assert_eq!(game.shared.turn, 1);
+        assert!(matches!(game.state, GameState::Play(_)));
+
+        let loaded = Game::load(&game.dump()).unwrap();
+        assert_eq!(&loaded.shared.board, board);
+        assert_eq!(loaded.shared.turn, 1);
+    }
+
+    #[test]
+    fn import_errors() {
+        assert_eq!(Game::from_sgf("(;GM[2])").unwrap_err(), SgfError::NotGo);
+        assert_eq!(
+            Game::from_sgf("(;SZ[9];B[aa").unwrap_err(),
+            SgfError::Syntax(12)
+        );
+        assert_eq!(
+            Game::from_sgf("(;SZ[9];B[aa];B[bb])").unwrap_err(),
+            SgfError::OutOfTurn { move_number: 1 }
+        );
+        assert_eq!(
+            Game::from_sgf("(;SZ[9];B[aa];W[aa])").unwrap_err(),
+            SgfError::IllegalMove {
+                move_number: 1,
+                error: MakeActionError::PointOccupied
+            }
+        );
+        assert_eq!(
+            Game::from_sgf("(;SZ[9]KM[6.25])").unwrap_err(),
+            SgfError::Unsupported("komi must be a multiple of 0.5")
+        );
+        assert_eq!(
+            Game::from_sgf("(;SZ[40])").unwrap_err(),
+            SgfError::Rules(RulesError::BoardSize(40, 40))
+        );
+        assert_eq!(
+            Game::from_sgf("(;SZ[9];B[aa];AB[bb])").unwrap_err(),
+            SgfError::Unsupported("setup after the first move")
+        );
+
+        // Main lines nested once per move are fine, up to a point.
+        let nested = |depth| format!("{}{}", "(;SZ[9]".repeat(depth), ")".repeat(depth));
+        assert!(Game::from_sgf(&nested(MAX_TREE_DEPTH)).is_ok());
+        assert_eq!(
+            Game::from_sgf(&nested(MAX_TREE_DEPTH + 1)).unwrap_err(),
+            SgfError::Syntax(7 * MAX_TREE_DEPTH)
+        );
+        let deep = "(".repeat(200_000);
+        assert!(matches!(
+            Game::from_sgf(&deep).unwrap_err(),
+            SgfError::Syntax(_)
+        ));
+    }
+
+    #[test]
+    fn half_points() {
+        assert_eq!(format_half_points(15), "7.5");
+        assert_eq!(format_half_points(-1), "-0.5");
+        assert_eq!(format_half_points(-4), "-2");
+        let result = GameResult::by_score(&tinyvec::tiny_vec![20, 35], true);
+        assert_eq!(format_result(&result), "W+7.5");
+    }
+}
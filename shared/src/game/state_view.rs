@@ -0,0 +1,146 @@
+//! The game state as one viewer may see it.
+//!
+//! `GameState` has everything in it, like the placements of every player in hidden move go.
+//! Views sent to clients carry a `GameStateView` instead, which drops what the viewer isn't
+//! allowed to know.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Color, GroupVec, Point, SharedState, VisibilityBoard};
+use crate::states::{FreePlacement, GameState, PlayState, ScoringState};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameStateView {
+    FreePlacement(FreePlacementView),
+    Play(PlayState),
+    Scoring(ScoringState),
+    Done(ScoringState),
+}
+
+/// Free placement without the boards, the viewer's own board is the board of the view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FreePlacementView {
+    /// Stones placed on the viewer's board
+    pub stones_placed: u32,
+    pub players_ready: Vec<bool>,
+    pub teams_share_stones: bool,
+    pub handicap: bool,
+}
+
+impl GameStateView {
+    pub fn new(state: &GameState, shared: &SharedState, player_id: u64) -> GameStateView {
+        match state {
+            GameState::FreePlacement(state) => {
+                GameStateView::FreePlacement(FreePlacementView::new(state, shared, player_id))
+            }
+            // Play and scoring only know what the board shows, the board view hides the rest.
+            GameState::Play(state) => GameStateView::Play(PlayState {
+                last_stone: last_stone_view(
+                    &state.last_stone,
+                    shared.board_visibility.as_ref(),
+                    team_of(shared, player_id),
+                ),
+                ..state.clone()
+            }),
+            GameState::Scoring(state) => GameStateView::Scoring(state.clone()),
+            GameState::Done(state) => GameStateView::Done(state.clone()),
+        }
+    }
+}
+
+/// The team the viewer plays for, None for spectators.
+pub fn team_of(shared: &SharedState, player_id: u64) -> Option<Color> {
+    shared
+        .seats
+        .iter()
+        .find(|x| x.player == Some(player_id))
+        .map(|x| x.team)
+}
+
+/// The stones of the last move, unless one of them is hidden from `team`. Otherwise the
+/// marker would give away where a phantom stone was played.
+pub fn last_stone_view(
+    last_stone: &Option<GroupVec<Point>>,
+    visibility: Option<&VisibilityBoard>,
+    team: Option<Color>,
+) -> Option<GroupVec<Point>> {
+    let stones = last_stone.as_ref()?;
+    let seen = |&point: &Point| {
+        visibility.is_none_or(|visibility| {
+            let seen = visibility.get_point(point);
+            seen.is_empty() || team.is_some_and(|team| seen.get(team.as_usize()))
+        })
+    };
+    if stones.iter().all(seen) {
+        Some(stones.clone())
+    } else {
+        None
+    }
+}
+
+impl FreePlacementView {
+    fn new(state: &FreePlacement, shared: &SharedState, player_id: u64) -> FreePlacementView {
+        FreePlacementView {
+            stones_placed: state
+                .view_index(&shared.seats, player_id)
+                .map_or(0, |idx| state.stones_placed[idx]),
+            players_ready: state.players_ready.clone(),
+            teams_share_stones: state.teams_share_stones,
+            handicap: state.handicap,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{ActionKind, Game, GameModifier, HiddenMoveGo};
+    use crate::message::ServerMessage;
+
+    /// A hidden move game where black placed `black` and white always the same stones.
+    fn hidden_game(black: &[(u32, u32)], start: bool) -> Game {
+        let mods = GameModifier {
+            hidden_move: Some(HiddenMoveGo {
+                placement_count: 2,
+                teams_share_stones: true,
+            }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        for &(x, y) in black {
+            game.make_action(1, ActionKind::Place(x, y)).unwrap();
+        }
+        game.make_action(2, ActionKind::Place(6, 6)).unwrap();
+        game.make_action(2, ActionKind::Place(6, 5)).unwrap();
+        if start {
+            game.make_action(1, ActionKind::Pass).unwrap();
+            game.make_action(2, ActionKind::Pass).unwrap();
+            game.make_action(1, ActionKind::Place(4, 4)).unwrap();
+        }
+        game
+    }
+
+    fn payloads(game: &Game, player_id: u64) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let status = ServerMessage::game_status(0, vec![1, 2], game.get_view(player_id)).pack();
+        let history = (0..=game.shared.board_history.len() as u32)
+            .filter_map(|turn| game.get_view_at(player_id, turn))
+            .map(|view| ServerMessage::BoardAt(view).pack())
+            .collect();
+        (status, history)
+    }
+
+    #[test]
+    fn placements_stay_secret() {
+        // Games that only differ by black's placements look the same to everyone else.
+        for &start in &[false, true] {
+            let one = hidden_game(&[(2, 2), (3, 3)], start);
+            let other = hidden_game(&[(2, 3), (3, 2)], start);
+            for &viewer in &[2, 100] {
+                assert_eq!(one.get_view(viewer), other.get_view(viewer));
+                assert_eq!(payloads(&one, viewer), payloads(&other, viewer));
+            }
+            assert_ne!(payloads(&one, 1), payloads(&other, 1));
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Zobrist hashing of board positions.
+//!
+//! Every stone of a color on a point and every visibility bit on a point has a fixed key.
+//! A position hashes to the XOR of the keys of everything on it, so placing or removing a
+//! stone only takes a single XOR. Stones and visibility are hashed separately so the rules
+//! can decide if hidden stones make a position different. The keys are derived from a
+//! constant seed with splitmix64, which makes hashes identical across runs and platforms
+//! and safe to store.
+
+use super::{Board, Color, Point, Visibility, VisibilityBoard};
+
+const SEED: u64 = 0x76_67_73_5f_7a_6f_62_72;
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Key for a stone of `color` at board index `idx`. The empty color has no key.
+pub fn stone_key(idx: usize, color: Color) -> u64 {
+    if color.is_empty() {
+        return 0;
+    }
+    splitmix64(SEED ^ ((idx as u64) << 8 | color.0 as u64))
+}
+
+/// Key for visibility `bit` (a seat or team that can see the stone) at board index `idx`.
+pub fn visibility_key(idx: usize, bit: usize) -> u64 {
+    splitmix64(SEED ^ ((idx as u64) << 8 | 0x80 | bit as u64))
+}
+
+fn visibility_keys(idx: usize, visibility: Visibility) -> u64 {
+    visibility
+        .into_iter()
+        .fold(0, |hash, bit| hash ^ visibility_key(idx, bit))
+}
+
+/// Hashes the stones of a board from scratch.
+pub fn hash(board: &Board) -> u64 {
+    board
+        .points
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (idx, &color)| hash ^ stone_key(idx, color))
+}
+
+/// Hashes the visibility of stones from scratch. Games without hidden stones hash to 0.
+pub fn visibility_hash(visibility: Option<&VisibilityBoard>) -> u64 {
+    visibility.map_or(0, |visibility| {
+        visibility
+            .points
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (idx, &bits)| hash ^ visibility_keys(idx, bits))
+    })
+}
+
+/// Sets a point on the board, updating `hash` to match.
+pub fn set_stone(board: &mut Board, hash: &mut u64, point: Point, color: Color) {
+    let idx = (point.1 * board.width + point.0) as usize;
+    let old = std::mem::replace(&mut board.points[idx], color);
+    *hash ^= stone_key(idx, old) ^ stone_key(idx, color);
+}
+
+/// Sets the visibility of a point, updating `hash` to match.
+pub fn set_visibility(
+    visibility: &mut VisibilityBoard,
+    hash: &mut u64,
+    point: Point,
+    value: Visibility,
+) {
+    let idx = (point.1 * visibility.width + point.0) as usize;
+    let old = std::mem::replace(&mut visibility.points[idx], value);
+    *hash ^= visibility_keys(idx, old) ^ visibility_keys(idx, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable() {
+        // Hashes get persisted, so the keys may never change.
+        assert_eq!(stone_key(0, Color(1)), 0xb2eb_0e19_122f_b71f);
+        assert_eq!(visibility_key(360, 3), 0xef48_4cdf_ed04_5b38);
+    }
+
+    #[test]
+    fn incremental_matches_full_hash() {
+        let mut board = Board::empty(9, 9);
+        let mut visibility = VisibilityBoard::empty(9, 9);
+        let mut stones = hash(&board);
+        let mut hidden = visibility_hash(Some(&visibility));
+        assert_eq!((stones, hidden), (0, 0));
+
+        let mut bits = Visibility::new();
+        bits.set(2, true);
+        set_stone(&mut board, &mut stones, (3, 4), Color(4));
+        set_stone(&mut board, &mut stones, (5, 5), Color(1));
+        set_visibility(&mut visibility, &mut hidden, (3, 4), bits);
+        set_stone(&mut board, &mut stones, (5, 5), Color(3));
+        assert_eq!(stones, hash(&board));
+        assert_eq!(hidden, visibility_hash(Some(&visibility)));
+
+        set_stone(&mut board, &mut stones, (5, 5), Color::empty());
+        set_visibility(&mut visibility, &mut hidden, (3, 4), Visibility::new());
+        assert_eq!(stones, stone_key(4 * 9 + 3, Color(4)));
+        assert_eq!(hidden, 0);
+    }
+
+    #[test]
+    fn game_hash_follows_captures_and_undo() {
+        use crate::game::{ActionKind, Game, GameModifier, MakeActionError};
+
+        let mut game = Game::standard(
+            &[1, 2],
+            tinyvec::tiny_vec![0, 0],
+            (5, 5),
+            GameModifier::default(),
+        )
+        .unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+
+        // Black and white build a ko around (1, 1) and (2, 1).
+        let moves = [
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (3, 1),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (1, 1),
+        ];
+        for (i, &(x, y)) in moves.iter().enumerate() {
+            game.make_action(1 + i as u64 % 2, ActionKind::Place(x, y))
+                .unwrap();
+            assert_eq!(game.shared.hash, hash(&game.shared.board));
+        }
+
+        // White just took the ko, black can't retake it right away.
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(2, 1)),
+            Err(MakeActionError::Ko)
+        );
+        assert_eq!(game.shared.hash, hash(&game.shared.board));
+
+        game.make_action(1, ActionKind::Cancel).unwrap();
+        assert_eq!(game.shared.hash, hash(&game.shared.board));
+    }
+}
@@ -16,6 +16,10 @@ pub enum GameAction {
     BoardAt(u32, u32),
     TakeSeat(u32),
     LeaveSeat(u32),
+    Resign,
+    RequestUndo,
+    AcceptUndo,
+    DeclineUndo,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -56,6 +60,8 @@ pub struct Profile {
 pub enum Error {
     /// This error means the client has to wait for x seconds before it can create a game
     GameStartTimer(u64),
+    /// The game setup was refused
+    Rules(game::RulesError),
     Other(Cow<'static, str>),
 }
 
@@ -85,15 +91,17 @@ pub enum ServerMessage {
         members: Vec<u64>,
         seats: Vec<(Option<u64>, u8)>,
         turn: u32,
-        // 19x19 vec, 0 = empty, 1 = black, 2 = white
+        // width x height vec, row by row, 0 = empty, 1 = black, 2 = white
         board: Vec<u8>,
         board_visibility: Option<Vec<u16>>,
         hidden_stones_left: u32,
         size: (u8, u8),
-        state: game::GameState,
+        state: game::GameStateView,
         mods: game::GameModifier,
         points: Vec<i32>,
         move_number: u32,
+        clock: Option<game::ClockView>,
+        result: Option<game::GameResult>,
     },
     BoardAt(game::GameHistory),
     Profile(Profile),
@@ -102,6 +110,32 @@ pub enum ServerMessage {
 }
 
 impl ServerMessage {
+    /// The status of a game as the owner of `view` sees it.
+    pub fn game_status(room_id: u32, members: Vec<u64>, view: game::GameView) -> ServerMessage {
+        ServerMessage::GameStatus {
+            room_id,
+            members,
+            seats: view
+                .seats
+                .into_iter()
+                .map(|x| (x.player, x.team.0))
+                .collect(),
+            turn: view.turn,
+            board: view.board.into_iter().map(|x| x.0).collect(),
+            board_visibility: view
+                .board_visibility
+                .map(|b| b.iter().map(|x| x.into_value()).collect()),
+            hidden_stones_left: view.hidden_stones_left,
+            size: view.size,
+            state: view.state,
+            mods: view.mods,
+            points: view.points.to_vec(),
+            move_number: view.move_number,
+            clock: view.clock,
+            result: view.result,
+        }
+    }
+
     pub fn pack(&self) -> Vec<u8> {
         serde_cbor::to_vec(self).expect("cbor fail")
     }
@@ -0,0 +1,77 @@
+use super::{GameSetup, Modifier};
+use crate::game::{CaptureTarget, Color, RulesError, SharedState};
+
+impl Modifier for CaptureTarget {
+    fn setup(&self, _setup: &mut GameSetup) -> Result<(), RulesError> {
+        if self.stones == 0 {
+            return Err(RulesError::CaptureTarget(self.stones));
+        }
+        Ok(())
+    }
+
+    fn winner(&self, shared: &SharedState) -> Option<Color> {
+        shared
+            .captures
+            .iter()
+            .position(|&captures| captures >= self.stones as i32)
+            .map(|idx| Color(idx as u8 + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        ActionKind, CaptureTarget, Color, Game, GameModifier, GameState, MakeActionError,
+        ResultReason, RulesError,
+    };
+    use crate::modifiers::tests::{new_game, play};
+
+    fn target_game(seats: &[u8], stones: u32) -> Result<Game, RulesError> {
+        let mods = GameModifier {
+            capture_target: Some(CaptureTarget { stones }),
+            ..GameModifier::default()
+        };
+        new_game(seats, (5, 5), mods)
+    }
+
+    #[test]
+    fn reaching_the_target_ends_the_game() {
+        assert!(matches!(
+            target_game(&[1, 2], 0),
+            Err(RulesError::CaptureTarget(0))
+        ));
+
+        let mut game = target_game(&[1, 2], 2).unwrap();
+        play(&mut game, &[(1, 0), (0, 0), (0, 1)]);
+        assert_eq!(&game.shared.captures[..], &[1, 0]);
+        assert!(matches!(game.state, GameState::Play(_)));
+
+        play(&mut game, &[(4, 4), (4, 3), (2, 2), (3, 4)]);
+        assert!(matches!(game.state, GameState::Done(_)));
+        let result = game.shared.result.clone().unwrap();
+        assert_eq!(result.winner, Some(Color(1)));
+        assert_eq!(result.reason, ResultReason::Captures);
+        let scores = result
+            .teams
+            .iter()
+            .map(|x| (x.team, x.score))
+            .collect::<Vec<_>>();
+        assert_eq!(scores, vec![(Color(1), 2), (Color(2), 0)]);
+        assert_eq!(
+            game.make_action(2, ActionKind::Pass),
+            Err(MakeActionError::GameDone)
+        );
+
+        let loaded = Game::load(&game.dump()).unwrap();
+        assert_eq!(loaded.shared.result, game.shared.result);
+    }
+
+    #[test]
+    fn any_color_can_win() {
+        let mut game = target_game(&[1, 2, 3], 1).unwrap();
+        play(&mut game, &[(0, 0), (2, 2), (1, 0), (4, 4), (2, 3), (0, 1)]);
+        let result = game.shared.result.clone().unwrap();
+        assert_eq!(result.winner, Some(Color(3)));
+        assert_eq!(result.teams[0].team, Color(3));
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use super::{GameSetup, Modifier};
+use crate::game::{Geometry, RulesError};
+
+impl Modifier for Geometry {
+    fn setup(&self, setup: &mut GameSetup) -> Result<(), RulesError> {
+        let board = &mut setup.board;
+        let links = self
+            .links(board.width, board.height)
+            .map_err(|reason| RulesError::Geometry(reason.into()))?;
+        board.links = links.map(Arc::new);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        ActionKind, Game, GameModifier, Geometry, Graph, MakeActionError, Point, RulesError,
+    };
+    use crate::modifiers::tests::new_game;
+
+    fn shaped(geometry: Geometry, size: (u8, u8)) -> Result<Game, RulesError> {
+        let mods = GameModifier {
+            geometry,
+            ..GameModifier::default()
+        };
+        new_game(&[1, 2], size, mods)
+    }
+
+    /// White plays `stone` and black every point of `around`, white passing meanwhile.
+    /// Returns whether the white stone is still there after each black move.
+    fn surround(game: &mut Game, stone: Point, around: &[Point]) -> Vec<bool> {
+        game.make_action(1, ActionKind::Place(around[0].0, around[0].1))
+            .unwrap();
+        game.make_action(2, ActionKind::Place(stone.0, stone.1))
+            .unwrap();
+        let mut alive = Vec::new();
+        for &(x, y) in &around[1..] {
+            game.make_action(1, ActionKind::Place(x, y)).unwrap();
+            alive.push(!game.shared.board.get_point(stone).is_empty());
+            game.make_action(2, ActionKind::Pass).unwrap();
+        }
+        alive
+    }
+
+    fn neighbours(game: &Game, point: Point) -> Vec<Point> {
+        game.shared.board.surrounding_points(point).collect()
+    }
+
+    #[test]
+    fn hex_stones_have_six_liberties() {
+        let mut game = shaped(Geometry::Hex, (5, 5)).unwrap();
+        assert_eq!(neighbours(&game, (0, 0)), vec![(1, 0), (0, 1)]);
+        assert_eq!(neighbours(&game, (4, 0)).len(), 3);
+
+        let around = neighbours(&game, (2, 2));
+        assert_eq!(around.len(), 6);
+        assert_eq!(
+            surround(&mut game, (2, 2), &around),
+            [true, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn cube_faces_join_at_the_edges() {
+        assert!(matches!(
+            shaped(Geometry::Cube, (9, 9)),
+            Err(RulesError::Geometry(_))
+        ));
+
+        let mut game = shaped(Geometry::Cube, (8, 6)).unwrap();
+        let board = &game.shared.board;
+        let points = (0..board.points.len())
+            .filter_map(|idx| board.idx_to_coord(idx))
+            .filter(|&p| board.point_within(p))
+            .collect::<Vec<_>>();
+        assert_eq!(points.len(), 24);
+        for &point in &points {
+            let around = neighbours(&game, point);
+            assert_eq!(around.len(), 4, "{:?}", point);
+            for n in around {
+                assert!(neighbours(&game, n).contains(&point), "{:?} {:?}", point, n);
+            }
+        }
+
+        // The corner of the top face touches the left face, which is far away on the board.
+        let around = neighbours(&game, (2, 0));
+        assert!(around.contains(&(0, 2)), "{:?}", around);
+        assert_eq!(surround(&mut game, (2, 0), &around), [true, true, false]);
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(0, 0)),
+            Err(MakeActionError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn custom_graphs() {
+        // A triangle with a tail, on a 3x3 board.
+        let graph = |points: &[Point], edges: &[(u32, u32)]| {
+            Geometry::Graph(Graph {
+                points: points.to_vec(),
+                edges: edges.to_vec(),
+            })
+        };
+        let triangle = graph(
+            &[(0, 0), (2, 0), (1, 2), (1, 1)],
+            &[(0, 1), (1, 2), (2, 0), (2, 3)],
+        );
+        let mut game = shaped(triangle, (3, 3)).unwrap();
+        assert_eq!(neighbours(&game, (1, 1)), vec![(1, 2)]);
+        let places = game
+            .legal_moves(1)
+            .into_iter()
+            .filter(|action| matches!(action, ActionKind::Place(..)))
+            .count();
+        assert_eq!(places, 4);
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(1, 0)),
+            Err(MakeActionError::OutOfBounds)
+        );
+        assert_eq!(surround(&mut game, (0, 0), &[(2, 0), (1, 2)]), [false]);
+
+        let broken = [
+            graph(&[(0, 0), (0, 0)], &[]),
+            graph(&[(0, 0), (3, 0)], &[]),
+            graph(&[(0, 0), (1, 0)], &[(0, 2)]),
+            graph(&[(0, 0), (1, 0)], &[(1, 1)]),
+        ];
+        for geometry in &broken {
+            assert!(
+                matches!(
+                    shaped(geometry.clone(), (3, 3)),
+                    Err(RulesError::Geometry(_))
+                ),
+                "{:?}",
+                geometry
+            );
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use super::{GameSetup, Modifier};
+use crate::game::{handicap_points, Color, Handicap, RulesError, ScoringRules};
+use crate::states::GameState;
+
+impl Modifier for Handicap {
+    fn setup(&self, setup: &mut GameSetup) -> Result<(), RulesError> {
+        if setup.komis.len() != 2 || !(2..=9).contains(&self.stones) {
+            return Err(RulesError::Handicap(self.stones));
+        }
+
+        if !self.free_placement {
+            let size = (setup.board.width as u8, setup.board.height as u8);
+            let points =
+                handicap_points(size, self.stones).ok_or(RulesError::Handicap(self.stones))?;
+            for point in points {
+                *setup.board.point_mut(point) = Color(1);
+            }
+        }
+
+        setup.komis[0] = 0;
+        setup.komis[1] = match setup.mods.scoring {
+            ScoringRules::Area => 1 + 2 * self.stones as i32,
+            ScoringRules::Territory => 1,
+        };
+        setup.turn = setup.seats.iter().position(|&t| t == 2).unwrap_or(0);
+        Ok(())
+    }
+
+    fn start_state(&self, setup: &GameSetup) -> Option<GameState> {
+        if self.free_placement {
+            Some(GameState::handicap_placement(
+                setup.seats.len(),
+                setup.board.clone(),
+            ))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use bitmaps::Bitmap;
+
+use super::{BoardView, GameSetup, Modifier};
+use crate::game::{Color, HiddenMoveGo};
+use crate::states::GameState;
+
+impl Modifier for HiddenMoveGo {
+    /// Everyone places their hidden stones first.
+    fn start_state(&self, setup: &GameSetup) -> Option<GameState> {
+        Some(GameState::free_placement(
+            setup.seats.len(),
+            setup.komis.len(),
+            setup.board.clone(),
+            self.teams_share_stones,
+        ))
+    }
+
+    /// Seats see the hidden stones of their team, spectators only the revealed stones.
+    fn view(&self, view: &mut BoardView, team: Option<Color>) {
+        let visibility = match &mut view.visibility {
+            Some(visibility) => visibility,
+            None => return,
+        };
+
+        let team = match team {
+            Some(team) => team,
+            None => {
+                for (point, visibility) in view.board.iter_mut().zip(visibility.iter()) {
+                    if !visibility.is_empty() {
+                        *point = Color::empty();
+                    }
+                }
+                view.visibility = None;
+                return;
+            }
+        };
+
+        for (point, visibility) in view.board.iter_mut().zip(visibility) {
+            if visibility.get(team.as_usize()) {
+                *point = team;
+                if visibility.len() > 1 {
+                    view.hidden_stones_left += 1;
+                }
+                *visibility = Bitmap::new();
+                visibility.set(team.as_usize(), true);
+            } else if !visibility.is_empty() {
+                view.hidden_stones_left += 1;
+                *point = Color::empty();
+                *visibility = Bitmap::new();
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Game variants.
+//!
+//! Every variant in `GameModifier` implements `Modifier`, whose hooks are called by the
+//! game at the points a variant can change. Hooks default to the standard game, so a
+//! variant only implements what it changes. A new variant gets its own module here and an
+//! entry in `Modifiers::new`.
+
+mod capture_target;
+mod geometry;
+mod handicap;
+mod hidden_move;
+mod n_plus_one;
+mod pixel;
+mod ponnuki;
+mod topology;
+mod visibility_mode;
+mod zen_go;
+
+pub use self::pixel::Pixel;
+pub use self::ponnuki::Ponnuki;
+
+use crate::game::{
+    Board, Color, GameModifier, Geometry, GroupVec, MakeActionError, MakeActionResult, Point,
+    RulesError, SharedState, Topology, Visibility, VisibilityBoard,
+};
+use crate::states::GameState;
+use std::fmt;
+use std::sync::Arc;
+
+/// A game being created by `Game::standard`.
+#[derive(Debug, Clone)]
+pub struct GameSetup<'a> {
+    pub seats: &'a [u8],
+    pub komis: GroupVec<i32>,
+    pub board: Board,
+    /// Stones hidden from some teams, for variants that hide stones during play
+    pub visibility: Option<VisibilityBoard>,
+    /// Seat that moves first
+    pub turn: usize,
+    pub mods: &'a GameModifier,
+}
+
+/// The board as a seat sees it during play.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardView {
+    pub board: Vec<Color>,
+    pub visibility: Option<Vec<Visibility>>,
+    /// Stones on the board the seat can't see
+    pub hidden_stones_left: u32,
+}
+
+pub trait Modifier: fmt::Debug + Send + Sync {
+    /// Checks the rules and changes the starting position, komis or first seat.
+    fn setup(&self, _setup: &mut GameSetup) -> Result<(), RulesError> {
+        Ok(())
+    }
+
+    /// The state the game starts in, if it doesn't start with play.
+    fn start_state(&self, _setup: &GameSetup) -> Option<GameState> {
+        None
+    }
+
+    /// Points a placement at `point` covers, if the variant doesn't place single stones.
+    fn footprint(
+        &self,
+        _board: &Board,
+        _point: Point,
+    ) -> Option<MakeActionResult<GroupVec<Point>>> {
+        None
+    }
+
+    /// Who sees a stone a seat of `team` places during play, if not everyone.
+    fn placed(&self, _team: Color) -> Option<Visibility> {
+        None
+    }
+
+    /// Who sees a stone a seat of `team` played on, when only the teams in `seen` saw it
+    /// before. Everyone by default.
+    fn reveal(&self, _seen: Visibility, _team: Color) -> Option<Visibility> {
+        None
+    }
+
+    /// Called for every chain `team` captures, once it is off the board.
+    fn captured(&self, _shared: &mut SharedState, _team: Color, _stones: &[Point]) {}
+
+    /// Whether the seat that just played `points_played` moves again.
+    fn extra_turn(&self, _shared: &mut SharedState, _points_played: &[Point]) -> bool {
+        false
+    }
+
+    /// Called after every action made during play.
+    fn after_action(&self, _shared: &mut SharedState) {}
+
+    /// Changes what a seat of `team`, or a spectator, sees of an unfinished game.
+    fn view(&self, _view: &mut BoardView, _team: Option<Color>) {}
+
+    /// The team that has won during play, for variants that end the game before scoring.
+    fn winner(&self, _shared: &SharedState) -> Option<Color> {
+        None
+    }
+
+    /// Whether the game has a winner, false for cooperative variants.
+    fn has_winner(&self) -> bool {
+        true
+    }
+}
+
+/// The variants a game is played with, built once when it is created instead of on every
+/// hook call. Hooks are called in this order, e.g. one color go recolors what hidden move
+/// go left visible. Clones share the list.
+#[derive(Clone)]
+pub struct Modifiers(Arc<[Box<dyn Modifier>]>);
+
+impl Modifiers {
+    pub fn new(mods: &GameModifier) -> Modifiers {
+        let mut active: Vec<Box<dyn Modifier>> = Vec::new();
+        if let Some(zen) = &mods.zen_go {
+            active.push(Box::new(zen.clone()));
+        }
+        if let Some(hidden) = &mods.hidden_move {
+            active.push(Box::new(hidden.clone()));
+        }
+        if let Some(handicap) = &mods.handicap {
+            active.push(Box::new(handicap.clone()));
+        }
+        if mods.pixel {
+            active.push(Box::new(Pixel));
+        }
+        if let Some(points) = mods.ponnuki_is_points {
+            active.push(Box::new(Ponnuki(points)));
+        }
+        if let Some(rule) = &mods.n_plus_one {
+            active.push(Box::new(rule.clone()));
+        }
+        if let Some(mode) = &mods.visibility_mode {
+            active.push(Box::new(mode.clone()));
+        }
+        if mods.topology != Topology::Plane {
+            active.push(Box::new(mods.topology));
+        }
+        if mods.geometry != Geometry::Square {
+            active.push(Box::new(mods.geometry.clone()));
+        }
+        if let Some(target) = &mods.capture_target {
+            active.push(Box::new(target.clone()));
+        }
+        Modifiers(active.into())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Modifier> {
+        self.0.iter().map(|m| &**m)
+    }
+}
+
+impl fmt::Debug for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Always equal, the `GameModifier` they are built from is compared instead.
+impl PartialEq for Modifiers {
+    fn eq(&self, _other: &Modifiers) -> bool {
+        true
+    }
+}
+
+/// Points a placement at `point` covers, a single stone unless a variant says otherwise.
+pub fn footprint(
+    modifiers: &Modifiers,
+    board: &Board,
+    point: Point,
+) -> MakeActionResult<GroupVec<Point>> {
+    if let Some(points) = modifiers.iter().find_map(|m| m.footprint(board, point)) {
+        return points;
+    }
+    if !board.point_within(point) {
+        return Err(MakeActionError::OutOfBounds);
+    }
+    let mut points = GroupVec::new();
+    points.push(point);
+    Ok(points)
+}
+
+/// Who sees a stone a seat of `team` places during play, everyone unless a variant says
+/// otherwise.
+pub fn placed(modifiers: &Modifiers, team: Color) -> Visibility {
+    modifiers
+        .iter()
+        .find_map(|m| m.placed(team))
+        .unwrap_or_default()
+}
+
+/// Who sees a stone a seat of `team` played on, everyone unless a variant says otherwise.
+pub fn reveal(modifiers: &Modifiers, seen: Visibility, team: Color) -> Visibility {
+    modifiers
+        .iter()
+        .find_map(|m| m.reveal(seen, team))
+        .unwrap_or_default()
+}
+
+/// Whether the seat that just played moves again. Every variant sees the move.
+pub fn extra_turn(shared: &mut SharedState, points_played: &[Point]) -> bool {
+    let mut extra = false;
+    let modifiers = shared.modifiers.clone();
+    for modifier in modifiers.iter() {
+        extra |= modifier.extra_turn(shared, points_played);
+    }
+    extra
+}
+
+/// The team that has won during play, if a variant says one has.
+pub fn winner(shared: &SharedState) -> Option<Color> {
+    shared.modifiers.iter().find_map(|m| m.winner(shared))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        ActionKind, Color, Game, GameModifier, HiddenMoveGo, Point, RulesError, VisibilityMode,
+    };
+
+    /// A game where seat `n` is held by player `n + 1`.
+    pub(super) fn new_game(
+        seats: &[u8],
+        size: (u8, u8),
+        mods: GameModifier,
+    ) -> Result<Game, RulesError> {
+        let komis = seats.iter().map(|_| 0).collect();
+        let mut game = Game::standard(seats, komis, size, mods)?;
+        for seat in 0..seats.len() {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+        Ok(game)
+    }
+
+    /// Plays `moves` in order, each by the seat to move.
+    pub(super) fn play(game: &mut Game, moves: &[Point]) {
+        for &(x, y) in moves {
+            let player = game.shared.seats[game.shared.turn].player.unwrap();
+            game.make_action(player, ActionKind::Place(x, y)).unwrap();
+        }
+    }
+
+    #[test]
+    fn one_color_recolors_hidden_move_view() {
+        let mods = GameModifier {
+            hidden_move: Some(HiddenMoveGo {
+                placement_count: 1,
+                teams_share_stones: true,
+            }),
+            visibility_mode: Some(VisibilityMode::OneColor),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (3, 3), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(2, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(1, ActionKind::Pass).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+        game.make_action(1, ActionKind::Place(1, 1)).unwrap();
+
+        // Black sees its own hidden stone and the played one, both in the one color.
+        let view = game.get_view(1);
+        assert_eq!(view.board[0], Color(2));
+        assert_eq!(view.board[4], Color(2));
+        assert!(view.board[8].is_empty());
+        assert_eq!(view.hidden_stones_left, 1);
+        let visibility = view.board_visibility.unwrap();
+        assert!(visibility[0].get(2) && visibility[0].len() == 1);
+        assert!(visibility[8].is_empty());
+
+        // Spectators only see the played stone.
+        let view = game.get_view(3);
+        assert_eq!(
+            view.board.iter().filter(|x| !x.is_empty()).count(),
+            1,
+            "{:?}",
+            view.board
+        );
+        assert_eq!(view.board_visibility, None);
+    }
+}
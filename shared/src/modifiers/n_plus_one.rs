@@ -0,0 +1,80 @@
+use super::Modifier;
+use crate::game::{zobrist, Board, NPlusOne, Point, SharedState, Visibility, VisibilityBoard};
+
+enum NPlusOneResult {
+    ExtraTurn,
+    Nothing,
+}
+
+impl Modifier for NPlusOne {
+    /// Making a line of exactly `length` stones gives an extra turn, and reveals the line.
+    fn extra_turn(&self, shared: &mut SharedState, points_played: &[Point]) -> bool {
+        matches!(
+            check(
+                points_played,
+                &shared.board,
+                shared.board_visibility.as_mut(),
+                &mut shared.visibility_hash,
+                self,
+            ),
+            NPlusOneResult::ExtraTurn
+        )
+    }
+}
+
+fn check(
+    points_played: &[Point],
+    board: &Board,
+    mut visibility: Option<&mut VisibilityBoard>,
+    visibility_hash: &mut u64,
+    rule: &NPlusOne,
+) -> NPlusOneResult {
+    let mut matched = false;
+
+    for &point_played in points_played {
+        // Vertical, horizontal and both diagonals
+        for &direction in &[(0, 1), (1, 0), (1, 1), (1, -1)] {
+            let line_points = line(board, point_played, direction);
+            if line_points.len() != rule.length as usize {
+                continue;
+            }
+
+            matched = true;
+            if let Some(visibility) = visibility.as_mut() {
+                for &p in &line_points {
+                    zobrist::set_visibility(visibility, visibility_hash, p, Visibility::new());
+                }
+            }
+        }
+    }
+
+    if matched {
+        return NPlusOneResult::ExtraTurn;
+    }
+
+    NPlusOneResult::Nothing
+}
+
+/// The stones of the same color in a row with `start`, in both ways along `(dx, dy)`.
+/// Lines follow the board around its wrapping edges.
+fn line(board: &Board, start: Point, (dx, dy): (i32, i32)) -> Vec<Point> {
+    let color = board.get_point(start);
+    let mut line_points = vec![start];
+
+    for &sign in &[1, -1] {
+        let mut p = start;
+        while let Some(next) = board.wrap((p.0 as i32 + sign * dx, p.1 as i32 + sign * dy)) {
+            if next == start {
+                // The line goes all the way around.
+                return line_points;
+            }
+            if board.get_point(next) != color {
+                break;
+            }
+            line_points.push(next);
+            p = next;
+        }
+    }
+
+    line_points
+}
@@ -0,0 +1,28 @@
+use super::Modifier;
+use crate::game::{Board, GroupVec, MakeActionError, MakeActionResult, Point};
+
+/// Pixel go places 2x2 blobs instead of single stones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pixel;
+
+impl Modifier for Pixel {
+    /// Coordinate 0,0 is outside the board, so a blob can hang over the top and left edges.
+    /// Parts of the blob off the board are dropped, or wrap around on wrapping boards.
+    fn footprint(&self, board: &Board, (x, y): Point) -> Option<MakeActionResult<GroupVec<Point>>> {
+        if x > board.width || y > board.height {
+            return Some(Err(MakeActionError::OutOfBounds));
+        }
+        let x = x as i32 - 1;
+        let y = y as i32 - 1;
+
+        let mut points = GroupVec::new();
+        for &point in &[(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)] {
+            if let Some(point) = board.wrap(point) {
+                if !points.contains(&point) {
+                    points.push(point);
+                }
+            }
+        }
+        Some(Ok(points))
+    }
+}
@@ -0,0 +1,19 @@
+use super::Modifier;
+use crate::game::{Color, Point, SharedState};
+
+/// "Ponnuki is 30 points": capturing a single stone with a ponnuki scores these points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ponnuki(pub i32);
+
+impl Modifier for Ponnuki {
+    fn captured(&self, shared: &mut SharedState, team: Color, stones: &[Point]) {
+        let board = &shared.board;
+        if stones.len() == 1
+            && board
+                .surrounding_points(stones[0])
+                .all(|p| board.get_point(p) == team)
+        {
+            shared.points[team.0 as usize - 1] += self.0;
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{GameSetup, Modifier};
+use crate::game::{RulesError, Topology};
+
+impl Modifier for Topology {
+    fn setup(&self, setup: &mut GameSetup) -> Result<(), RulesError> {
+        setup.board.topology = *self;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{ActionKind, Color, Game, GameModifier, NPlusOne, Topology};
+    use crate::modifiers::tests::{new_game, play};
+
+    fn wrapped(topology: Topology, mods: GameModifier) -> Game {
+        new_game(&[1, 2], (5, 5), GameModifier { topology, ..mods }).unwrap()
+    }
+
+    fn empty(game: &Game, point: (u32, u32)) -> bool {
+        game.shared.board.get_point(point).is_empty()
+    }
+
+    #[test]
+    fn captures_across_the_seam() {
+        // The white stones at the left and right edges are one chain once the sides wrap.
+        let surround = [(0, 1), (0, 2), (0, 3), (4, 2), (1, 2)];
+        let finish = [(2, 0), (4, 1), (2, 4), (4, 3), (0, 4), (3, 2)];
+        for &topology in &[Topology::Plane, Topology::Cylinder, Topology::Torus] {
+            let mut game = wrapped(topology, GameModifier::default());
+            play(&mut game, &surround);
+            let wraps = topology != Topology::Plane;
+            assert_eq!(empty(&game, (0, 2)), !wraps, "{:?}", topology);
+            if wraps {
+                play(&mut game, &finish);
+                assert!(
+                    empty(&game, (0, 2)) && empty(&game, (4, 2)),
+                    "{:?}",
+                    topology
+                );
+            }
+        }
+
+        // Only the torus wraps from the top edge to the bottom one.
+        let moves = [(1, 0), (2, 0), (3, 0), (0, 2), (2, 1)];
+        for &(topology, captured) in &[(Topology::Cylinder, true), (Topology::Torus, false)] {
+            let mut game = wrapped(topology, GameModifier::default());
+            play(&mut game, &moves);
+            assert_eq!(empty(&game, (2, 0)), captured, "{:?}", topology);
+        }
+        let mut game = wrapped(Topology::Torus, GameModifier::default());
+        play(&mut game, &moves);
+        play(&mut game, &[(4, 2), (2, 4)]);
+        assert!(empty(&game, (2, 0)));
+    }
+
+    #[test]
+    fn pixels_and_lines_wrap() {
+        let pixel = GameModifier {
+            pixel: true,
+            ..GameModifier::default()
+        };
+        let mut game = wrapped(Topology::Torus, pixel);
+        game.make_action(1, ActionKind::Place(0, 0)).unwrap();
+        let black = game
+            .shared
+            .board
+            .points
+            .iter()
+            .filter(|&&x| x == Color(1))
+            .count();
+        assert_eq!(black, 4);
+        assert_eq!(game.shared.board.get_point((4, 4)), Color(1));
+
+        // A line of three across the seam gives black another turn.
+        let n_plus_one = GameModifier {
+            n_plus_one: Some(NPlusOne { length: 3 }),
+            ..GameModifier::default()
+        };
+        let mut game = wrapped(Topology::Cylinder, n_plus_one);
+        play(&mut game, &[(4, 2), (2, 0), (0, 2), (2, 4)]);
+        assert_eq!(game.shared.turn, 0);
+        game.make_action(1, ActionKind::Place(1, 2)).unwrap();
+        assert_eq!(game.shared.turn, 0);
+    }
+}
@@ -0,0 +1,219 @@
+use bitmaps::Bitmap;
+
+use super::{BoardView, GameSetup, Modifier};
+use crate::game::{Color, RulesError, Visibility, VisibilityMode};
+
+// Set color to white.
+// TODO: Change this to black once the client supports selecting the color
+const ONE_COLOR_TEAM: Color = Color(2);
+
+impl Modifier for VisibilityMode {
+    fn setup(&self, setup: &mut GameSetup) -> Result<(), RulesError> {
+        if let VisibilityMode::Phantom = self {
+            setup.visibility = Some(setup.board.blank());
+        }
+        Ok(())
+    }
+
+    /// Phantom stones are only seen by their own team.
+    fn placed(&self, team: Color) -> Option<Visibility> {
+        match self {
+            VisibilityMode::OneColor => None,
+            VisibilityMode::Phantom => {
+                let mut seen = Bitmap::new();
+                seen.set(team.as_usize(), true);
+                Some(seen)
+            }
+        }
+    }
+
+    /// A phantom stone is shown to the team that ran into it, stones it already sees stay
+    /// as they are.
+    fn reveal(&self, mut seen: Visibility, team: Color) -> Option<Visibility> {
+        match self {
+            VisibilityMode::OneColor => None,
+            VisibilityMode::Phantom => {
+                if !seen.is_empty() {
+                    seen.set(team.as_usize(), true);
+                }
+                Some(seen)
+            }
+        }
+    }
+
+    /// One color shows every stone in the same color, hidden stones included. Phantom
+    /// leaves out the stones the viewer's team hasn't seen, spectators only see the stones
+    /// everyone has seen.
+    fn view(&self, view: &mut BoardView, team: Option<Color>) {
+        match self {
+            VisibilityMode::OneColor => {
+                for point in &mut view.board {
+                    if !point.is_empty() {
+                        *point = ONE_COLOR_TEAM;
+                    }
+                }
+                for visibility in view.visibility.iter_mut().flatten() {
+                    if !visibility.is_empty() {
+                        *visibility = Bitmap::new();
+                        visibility.set(ONE_COLOR_TEAM.as_usize(), true);
+                    }
+                }
+            }
+            VisibilityMode::Phantom => {
+                if let Some(visibility) = view.visibility.take() {
+                    for (point, seen) in view.board.iter_mut().zip(visibility) {
+                        if !seen.is_empty() && !team.is_some_and(|team| seen.get(team.as_usize())) {
+                            *point = Color::empty();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        ActionKind, Color, Game, GameModifier, GameStateView, MakeActionError, Setup,
+        VisibilityMode,
+    };
+
+    fn phantom_game() -> Game {
+        let mods = GameModifier {
+            visibility_mode: Some(VisibilityMode::Phantom),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (5, 5), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game
+    }
+
+    /// Points with a stone on them as `player_id` sees the game.
+    fn stones(game: &Game, player_id: u64) -> Vec<usize> {
+        let view = game.get_view(player_id);
+        assert_eq!(view.board_visibility, None);
+        (0..view.board.len())
+            .filter(|&idx| !view.board[idx].is_empty())
+            .collect()
+    }
+
+    fn last_stone(game: &Game, player_id: u64) -> bool {
+        match game.get_view(player_id).state {
+            GameStateView::Play(state) => state.last_stone.is_some(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn teams_see_their_own_stones() {
+        let mut game = phantom_game();
+        game.make_action(1, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(2, ActionKind::Place(4, 4)).unwrap();
+        assert_eq!(stones(&game, 1), vec![0]);
+        assert_eq!(stones(&game, 2), vec![24]);
+        assert_eq!(stones(&game, 3), vec![]);
+        assert!(!last_stone(&game, 1) && last_stone(&game, 2));
+        assert!(game.get_view_at(1, 2).unwrap().last_stone.is_none());
+        assert!(game.is_legal(1, &ActionKind::Place(4, 4)));
+
+        // Running into the white stone shows it to black, who plays again.
+        game.make_action(1, ActionKind::Place(4, 4)).unwrap();
+        assert_eq!(game.shared.turn, 0);
+        assert_eq!(stones(&game, 1), vec![0, 24]);
+        assert_eq!(stones(&game, 3), vec![]);
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(4, 4)),
+            Err(MakeActionError::PointOccupied)
+        );
+        game.make_action(1, ActionKind::Place(3, 4)).unwrap();
+        assert_eq!(game.shared.turn, 1);
+        assert_eq!(stones(&game, 2), vec![24]);
+
+        // The capture shows the stones around it to everyone.
+        game.make_action(2, ActionKind::Pass).unwrap();
+        game.make_action(1, ActionKind::Place(4, 3)).unwrap();
+        assert!(game.shared.board.get_point((4, 4)).is_empty());
+        assert_eq!(stones(&game, 3), vec![19, 23]);
+        assert!(last_stone(&game, 3));
+
+        let loaded = Game::load(&game.dump()).unwrap();
+        assert_eq!(loaded.get_view(2), game.get_view(2));
+    }
+
+    #[test]
+    fn stones_shown_by_a_capture_dont_allow_retaking_a_ko() {
+        let mut game = phantom_game();
+        let moves = [
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (3, 1),
+            (1, 2),
+            (2, 2),
+            (2, 1),
+            (1, 1),
+        ];
+        for (idx, &(x, y)) in moves.iter().enumerate() {
+            game.make_action(idx as u64 % 2 + 1, ActionKind::Place(x, y))
+                .unwrap();
+        }
+        assert!(game.shared.board.get_point((2, 1)).is_empty());
+
+        assert!(!game.is_legal(1, &ActionKind::Place(2, 1)));
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(2, 1)),
+            Err(MakeActionError::Ko)
+        );
+    }
+
+    #[test]
+    fn refused_moves_leave_no_trace() {
+        let mut game = phantom_game();
+        let white = [(2, 0), (1, 1), (3, 1), (2, 2), (3, 4), (4, 3)];
+        let black = [(1, 0), (0, 1), (1, 2)];
+        let stones = white
+            .iter()
+            .map(|&p| (p, Color(2)))
+            .chain(black.iter().map(|&p| (p, Color(1))))
+            .collect();
+        game.setup(Setup { stones, turn: 0 }).unwrap();
+        let visibility = game.shared.board_visibility.clone();
+        let hash = game.shared.visibility_hash;
+
+        // Every stone is known, so nothing is shown by trying.
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(4, 4)),
+            Err(MakeActionError::Suicide)
+        );
+        assert_eq!(game.shared.board_visibility, visibility);
+        assert_eq!(game.shared.visibility_hash, hash);
+
+        // Black takes at (2, 1), white can't take back at (1, 1) right away.
+        game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+        let visibility = game.shared.board_visibility.clone();
+        let hash = game.shared.visibility_hash;
+        assert_eq!(
+            game.make_action(2, ActionKind::Place(1, 1)),
+            Err(MakeActionError::Ko)
+        );
+        assert_eq!(game.shared.board_visibility, visibility);
+        assert_eq!(game.shared.visibility_hash, hash);
+    }
+
+    #[test]
+    fn everything_shows_once_the_game_ends() {
+        let mut game = phantom_game();
+        game.make_action(1, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(2, ActionKind::Place(4, 4)).unwrap();
+        for player in &[1, 2, 1, 2] {
+            game.make_action(*player, ActionKind::Pass).unwrap();
+        }
+        assert!(game.shared.result.is_some());
+        assert_eq!(stones(&game, 3), vec![0, 24]);
+        let history = game.get_view_at(3, 2).unwrap();
+        assert_eq!(history.board[24], Color(2).0);
+        assert!(history.last_stone.is_some());
+    }
+}
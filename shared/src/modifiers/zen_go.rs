@@ -0,0 +1,23 @@
+use super::{GameSetup, Modifier};
+use crate::game::{Color, RulesError, SharedState, ZenGo};
+
+impl Modifier for ZenGo {
+    fn setup(&self, setup: &mut GameSetup) -> Result<(), RulesError> {
+        if self.color_count == 0 || self.color_count as usize > setup.komis.len() {
+            return Err(RulesError::ZenColorCount(self.color_count));
+        }
+        Ok(())
+    }
+
+    /// Every seat plays the color of the move.
+    fn after_action(&self, shared: &mut SharedState) {
+        let move_number = shared.board_history.len() - 1;
+        for seat in &mut shared.seats {
+            seat.team = Color((move_number % self.color_count as usize) as u8 + 1);
+        }
+    }
+
+    fn has_winner(&self) -> bool {
+        false
+    }
+}
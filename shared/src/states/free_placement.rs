@@ -1,7 +1,8 @@
 use crate::game::{
-    ActionChange, ActionKind, Board, BoardHistory, Color, GameState, MakeActionError,
-    MakeActionResult, Seat, SharedState, VisibilityBoard,
+    zobrist, ActionChange, ActionKind, Board, Chains, Color, GameState, MakeActionError,
+    MakeActionResult, Point, Seat, SharedState, VisibilityBoard,
 };
+use crate::modifiers;
 use serde::{Deserialize, Serialize};
 
 use itertools::izip;
@@ -13,6 +14,8 @@ pub struct FreePlacement {
     pub stones_placed: Vec<u32>,
     pub players_ready: Vec<bool>,
     pub teams_share_stones: bool,
+    /// Black places handicap stones for everyone to see, on the only board.
+    pub handicap: bool,
 }
 
 impl FreePlacement {
@@ -32,6 +35,54 @@ impl FreePlacement {
             stones_placed: vec![0; count],
             players_ready: vec![false; seat_count],
             teams_share_stones,
+            handicap: false,
+        }
+    }
+
+    pub fn handicap(seat_count: usize, board: Board) -> Self {
+        FreePlacement {
+            boards: vec![board],
+            stones_placed: vec![0],
+            players_ready: vec![false; seat_count],
+            teams_share_stones: true,
+            handicap: true,
+        }
+    }
+
+    /// Index of the board `player_id` sees. Everyone sees the handicap stones, spectators
+    /// don't see any placements.
+    pub fn view_index(&self, seats: &[Seat], player_id: u64) -> Option<usize> {
+        if self.handicap {
+            return Some(0);
+        }
+        let (seat_idx, seat) = seats
+            .iter()
+            .enumerate()
+            .find(|(_, x)| x.player == Some(player_id))?;
+        Some(if self.teams_share_stones {
+            seat.team.0 as usize - 1
+        } else {
+            seat_idx
+        })
+    }
+
+    /// Stones every board gets.
+    pub fn placement_count(&self, shared: &SharedState) -> u32 {
+        match &shared.mods.handicap {
+            Some(handicap) if self.handicap => handicap.stones,
+            _ => shared.mods.hidden_move.as_ref().unwrap().placement_count,
+        }
+    }
+
+    fn seat<'a>(&self, seats: &'a [Seat], player_id: u64) -> MakeActionResult<(usize, &'a Seat)> {
+        if self.handicap {
+            seats
+                .iter()
+                .enumerate()
+                .find(|(_, x)| x.player == Some(player_id) && x.team == Color(1))
+                .ok_or(MakeActionError::NotTurn)
+        } else {
+            Ok(get_seat(seats, player_id))
         }
     }
 
@@ -39,10 +90,11 @@ impl FreePlacement {
         &mut self,
         shared: &mut SharedState,
         player_id: u64,
-        (x, y): (u32, u32),
+        point: Point,
     ) -> MakeActionResult {
-        let (seat_idx, active_seat) = get_seat(&shared.seats, player_id);
+        let (seat_idx, active_seat) = self.seat(&shared.seats, player_id)?;
         let team = active_seat.team;
+        let placement_count = self.placement_count(shared);
 
         let board = if self.teams_share_stones {
             &mut self.boards[team.0 as usize - 1]
@@ -55,83 +107,77 @@ impl FreePlacement {
             &mut self.stones_placed[seat_idx]
         };
 
-        if *stones_placed >= shared.mods.hidden_move.as_ref().unwrap().placement_count {
+        if *stones_placed >= placement_count {
             return Err(MakeActionError::PointOccupied);
         }
 
-        if shared.mods.pixel {
-            // In pixel mode coordinate 0,0 is outside the board.
-            // This is to adjust for it.
-
-            if x > board.width || y > board.height {
-                return Err(MakeActionError::OutOfBounds);
-            }
-            let x = x as i32 - 1;
-            let y = y as i32 - 1;
-
-            let mut any_placed = false;
-            for &(x, y) in &[(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)] {
-                if x < 0 || y < 0 {
-                    continue;
-                }
-                let coord = (x as u32, y as u32);
-                if !board.point_within(coord) {
-                    continue;
-                }
-
-                let point = board.point_mut(coord);
-                if !point.is_empty() {
-                    continue;
-                }
+        let footprint = modifiers::footprint(&shared.modifiers, board, point)?;
+        let mut any_placed = false;
+        for coord in footprint {
+            let point = board.point_mut(coord);
+            if point.is_empty() {
                 *point = active_seat.team;
                 any_placed = true;
             }
-            if !any_placed {
-                return Err(MakeActionError::PointOccupied);
-            }
-        } else {
-            if !board.point_within((x, y)) {
-                return Err(MakeActionError::OutOfBounds);
-            }
-
-            // TODO: don't repeat yourself
-            let point = board.point_mut((x, y));
-            if !point.is_empty() {
-                return Err(MakeActionError::PointOccupied);
-            }
-
-            *point = active_seat.team;
+        }
+        if !any_placed {
+            return Err(MakeActionError::PointOccupied);
         }
 
         *stones_placed += 1;
 
+        if self.handicap && *stones_placed == placement_count {
+            return Ok(self.start_handicap_game(shared));
+        }
+
         Ok(ActionChange::None)
     }
 
+    /// The handicap stones are all placed, white moves first.
+    fn start_handicap_game(&self, shared: &mut SharedState) -> ActionChange {
+        let board = self.boards[0].clone();
+        shared.hash = zobrist::hash(&board);
+        shared.chains = Chains::new(&board);
+        shared.board = board;
+
+        let state = GameState::play(shared.seats.len());
+
+        shared.turn = shared
+            .seats
+            .iter()
+            .position(|x| x.team == Color(2))
+            .unwrap_or(0);
+        shared.reset_history(state.clone());
+
+        ActionChange::SwapState(state)
+    }
+
     pub fn make_action_pass(
         &mut self,
         shared: &mut SharedState,
         player_id: u64,
     ) -> MakeActionResult {
+        if self.handicap {
+            // Play starts once every handicap stone is placed.
+            return Err(MakeActionError::NotTurn);
+        }
+
         let (seat_idx, _active_seat) = get_seat(&shared.seats, player_id);
         self.players_ready[seat_idx] = true;
 
         if self.players_ready.iter().all(|x| *x) {
             let (board, visibility) = self.build_board(shared.board.clone());
 
+            shared.hash = zobrist::hash(&board);
+            shared.visibility_hash = zobrist::visibility_hash(Some(&visibility));
+            shared.chains = Chains::new(&board);
             shared.board = board;
             shared.board_visibility = Some(visibility);
 
             let state = GameState::play(shared.seats.len());
 
-            shared.board_history = vec![BoardHistory {
-                hash: shared.board.hash(),
-                board: shared.board.clone(),
-                board_visibility: shared.board_visibility.clone(),
-                state: state.clone(),
-                points: shared.points.clone(),
-                turn: 0,
-            }];
+            shared.turn = 0;
+            shared.reset_history(state.clone());
 
             return Ok(ActionChange::SwapState(state));
         }
@@ -171,7 +217,7 @@ impl FreePlacement {
         shared: &mut SharedState,
         player_id: u64,
     ) -> MakeActionResult {
-        let (seat_idx, active_seat) = get_seat(&shared.seats, player_id);
+        let (seat_idx, active_seat) = self.seat(&shared.seats, player_id)?;
         let team = active_seat.team;
 
         let board = if self.teams_share_stones {
@@ -202,6 +248,9 @@ impl FreePlacement {
             ActionKind::Place(x, y) => self.make_action_place(shared, player_id, (x, y)),
             ActionKind::Pass => self.make_action_pass(shared, player_id),
             ActionKind::Cancel => self.make_action_cancel(shared, player_id),
+            ActionKind::Resign => super::resign(shared, player_id),
+            ActionKind::RequestUndo => Err(MakeActionError::UndoNotAllowed),
+            ActionKind::AcceptUndo | ActionKind::DeclineUndo => Err(MakeActionError::NoUndoRequest),
         }
     }
 }
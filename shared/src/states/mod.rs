@@ -7,7 +7,9 @@ pub use self::play::PlayState;
 pub use self::scoring::ScoringState;
 
 use crate::assume::AssumeFrom;
-use crate::game::Board;
+use crate::game::{
+    ActionChange, Board, GameResult, MakeActionError, MakeActionResult, ResultReason, SharedState,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -33,15 +35,40 @@ impl GameState {
         ))
     }
 
+    pub fn handicap_placement(seat_count: usize, board: Board) -> Self {
+        GameState::FreePlacement(FreePlacement::handicap(seat_count, board))
+    }
+
     pub fn play(seat_count: usize) -> Self {
         GameState::Play(PlayState::new(seat_count))
     }
 
-    pub fn scoring(board: &Board, seat_count: usize, scores: &[i32]) -> Self {
-        GameState::Scoring(ScoringState::new(board, seat_count, scores))
+    pub fn scoring(shared: &SharedState) -> Self {
+        GameState::Scoring(ScoringState::new(shared))
     }
 }
 
+/// The team of `player_id` resigns, ending the game. A player holding seats of several
+/// teams resigns for the seat to move.
+pub fn resign(shared: &mut SharedState, player_id: u64) -> MakeActionResult {
+    let seat = if shared.seats[shared.turn].player == Some(player_id) {
+        shared.turn
+    } else {
+        shared
+            .seats
+            .iter()
+            .position(|x| x.player == Some(player_id))
+            .ok_or(MakeActionError::NotPlayer)?
+    };
+    let scoring = ScoringState::new(shared);
+    shared.result = Some(GameResult::lost_by(
+        shared.seats[seat].team,
+        ResultReason::Resignation,
+        &scoring.scores,
+    ));
+    Ok(ActionChange::SwapState(GameState::Done(scoring)))
+}
+
 assume!(GameState);
 assume!(GameState, Play(x) => x, PlayState);
 assume!(GameState, Scoring(x) => x, ScoringState);
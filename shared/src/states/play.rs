@@ -1,21 +1,34 @@
-mod n_plus_one;
-
 use crate::game::{
-    find_groups, ActionChange, ActionKind, Board, BoardHistory, Color, GameState, Group, GroupVec,
-    MakeActionError, MakeActionResult, Point, Seat, SharedState, VisibilityBoard,
+    zobrist, ActionChange, ActionKind, Board, BoardHistory, Chains, Color, GameResult, GameState,
+    GroupVec, KoRule, MakeActionError, MakeActionResult, Point, Seat, SharedState, UndoPolicy,
+    VisibilityBoard, VisibilityMode,
 };
+use crate::modifiers;
+use crate::states::ScoringState;
 use serde::{Deserialize, Serialize};
 
 use bitmaps::Bitmap;
-use tinyvec::tiny_vec;
 
 type Revealed = bool;
+type Suicided = bool;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlayState {
     // TODO: use smallvec?
     pub players_passed: Vec<bool>,
     pub last_stone: Option<GroupVec<(u32, u32)>>,
+    /// Point the next seat can't play on because it would retake a ko
+    pub ko_point: Option<Point>,
+    pub undo_request: Option<UndoRequest>,
+}
+
+/// An undo waiting for the other seats to accept it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UndoRequest {
+    /// Seat that asked for the undo
+    pub seat: usize,
+    /// For every seat, whether it accepted. Seats of the asking player start accepted.
+    pub accepted: Vec<bool>,
 }
 
 impl PlayState {
@@ -23,177 +36,189 @@ impl PlayState {
         PlayState {
             players_passed: vec![false; seat_count],
             last_stone: None,
+            ko_point: None,
+            undo_request: None,
         }
     }
 
     fn place_stone(
         &mut self,
         shared: &mut SharedState,
-        (x, y): Point,
+        point: Point,
     ) -> MakeActionResult<GroupVec<Point>> {
         let active_seat = get_active_seat(shared);
+        let footprint = modifiers::footprint(&shared.modifiers, &shared.board, point)?;
         let mut points_played = GroupVec::new();
 
-        if shared.mods.pixel {
-            // In pixel mode coordinate 0,0 is outside the board.
-            // This is to adjust for it.
-
-            if x > shared.board.width || y > shared.board.height {
-                return Err(MakeActionError::OutOfBounds);
-            }
-            let x = x as i32 - 1;
-            let y = y as i32 - 1;
-
-            let mut any_placed = false;
-            let mut any_revealed = false;
-            for &(x, y) in &[(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)] {
-                if x < 0 || y < 0 {
-                    continue;
-                }
-                let coord = (x as u32, y as u32);
-                if !shared.board.point_within(coord) {
-                    continue;
-                }
-
-                let point = shared.board.point_mut(coord);
-                if let Some(visibility) = &mut shared.board_visibility {
-                    if !visibility.get_point(coord).is_empty() {
-                        any_revealed = true;
-                        points_played.push(coord);
-                    }
-                    *visibility.point_mut(coord) = Bitmap::new();
-                }
-                if !point.is_empty() {
-                    continue;
-                }
-                *point = active_seat.team;
-                points_played.push(coord);
-                any_placed = true;
-            }
-            if !any_placed {
-                if any_revealed {
-                    self.last_stone = Some(points_played);
-                    return Ok(GroupVec::new());
-                }
-                return Err(MakeActionError::PointOccupied);
-            }
-        } else {
-            if !shared.board.point_within((x, y)) {
-                return Err(MakeActionError::OutOfBounds);
+        let mut any_placed = false;
+        let mut any_revealed = false;
+        for coord in footprint {
+            let occupied = !shared.board.get_point(coord).is_empty();
+            let mut revealed = false;
+            if let Some(visibility) = &mut shared.board_visibility {
+                let seen = visibility.get_point(coord);
+                let now = if occupied {
+                    modifiers::reveal(&shared.modifiers, seen, active_seat.team)
+                } else {
+                    modifiers::placed(&shared.modifiers, active_seat.team)
+                };
+                revealed = !seen.is_empty() && now != seen;
+                zobrist::set_visibility(visibility, &mut shared.visibility_hash, coord, now);
             }
-
-            // TODO: don't repeat yourself
-            let point = shared.board.point_mut((x, y));
-            let revealed = if let Some(visibility) = &mut shared.board_visibility {
-                let revealed = !visibility.get_point((x, y)).is_empty();
-                *visibility.point_mut((x, y)) = Bitmap::new();
-                revealed
-            } else {
-                false
-            };
-            if !point.is_empty() {
+            any_revealed = any_revealed || revealed;
+            if occupied {
                 if revealed {
-                    self.last_stone = Some(tiny_vec![[Point; 8] => (x, y)]);
-                    return Ok(points_played);
+                    points_played.push(coord);
                 }
-                return Err(MakeActionError::PointOccupied);
+                continue;
             }
+            zobrist::set_stone(&mut shared.board, &mut shared.hash, coord, active_seat.team);
+            shared.chains.add_stone(coord, active_seat.team);
+            points_played.push(coord);
+            any_placed = true;
+        }
 
-            *point = active_seat.team;
-            points_played.push((x, y));
+        if !any_placed {
+            if any_revealed {
+                self.last_stone = Some(points_played);
+                return Ok(GroupVec::new());
+            }
+            return Err(MakeActionError::PointOccupied);
         }
 
         Ok(points_played)
     }
 
+    /// Removes the chains left without liberties, the opponents' first. Of the own chains
+    /// only the stones just played are taken back, unless the rules allow suicide.
     fn capture(
         &self,
         shared: &mut SharedState,
         points_played: &mut GroupVec<Point>,
-    ) -> (usize, Revealed) {
+    ) -> (usize, Revealed, Suicided) {
         let active_seat = get_active_seat(shared);
         let mut captures = 0;
         let mut revealed = false;
+        let mut suicided = false;
 
-        let groups = find_groups(&shared.board);
-        let dead_opponents = groups
+        let dead_opponents = shared
+            .chains
             .iter()
-            .filter(|g| g.liberties == 0 && g.team != active_seat.team);
-
-        let board = &mut shared.board;
-
-        for group in dead_opponents {
-            for point in &group.points {
-                *board.point_mut(*point) = Color::empty();
+            .filter(|c| c.liberties.is_empty() && c.color != active_seat.team)
+            .map(|c| c.stones[0])
+            .collect::<Vec<_>>();
+
+        for point in dead_opponents {
+            let chain = shared.chains.remove_chain(point);
+            let board = &mut shared.board;
+            for &point in &chain.stones {
+                zobrist::set_stone(board, &mut shared.hash, point, Color::empty());
                 captures += 1;
             }
-            let reveals = reveal_group(&mut shared.board_visibility, group, board);
+            shared.captures[active_seat.team.0 as usize - 1] += chain.stones.len() as i32;
+            let reveals = reveal_group(
+                &mut shared.board_visibility,
+                &mut shared.visibility_hash,
+                &chain.stones,
+                board,
+            );
             revealed = revealed || reveals;
 
-            if let Some(ponnuki) = shared.mods.ponnuki_is_points {
-                if group.points.len() == 1
-                    && board
-                        .surrounding_points(group.points[0])
-                        .all(|p| board.get_point(p) == active_seat.team)
-                {
-                    shared.points[active_seat.team.0 as usize - 1] += ponnuki;
-                }
+            let modifiers = shared.modifiers.clone();
+            for modifier in modifiers.iter() {
+                modifier.captured(shared, active_seat.team, &chain.stones);
             }
         }
 
-        // TODO: only re-scan own previously dead grouos
-        let groups = find_groups(board);
-        let dead_own = groups
+        let dead_own = shared
+            .chains
             .iter()
-            .filter(|g| g.liberties == 0 && g.team == active_seat.team);
-
-        for group in dead_own {
-            for point in &group.points {
-                if points_played.contains(point) {
+            .filter(|c| c.liberties.is_empty() && c.color == active_seat.team)
+            .map(|c| c.stones.clone())
+            .collect::<Vec<_>>();
+
+        let mut taken_back = false;
+        for stones in &dead_own {
+            let board = &mut shared.board;
+            if shared.mods.suicide && stones.len() > 1 {
+                // The stones are prisoners of every team that surrounded them.
+                let mut capturers = GroupVec::<Color>::new();
+                for p in stones.iter().flat_map(|&p| board.surrounding_points(p)) {
+                    let color = board.get_point(p);
+                    if !color.is_empty() && color != active_seat.team && !capturers.contains(&color)
+                    {
+                        capturers.push(color);
+                    }
+                }
+                for team in capturers {
+                    shared.captures[team.0 as usize - 1] += stones.len() as i32;
+                }
+                shared.chains.remove_chain(stones[0]);
+                for point in stones {
                     points_played.retain(|x| x != point);
-                    *board.point_mut(*point) = Color::empty();
+                    zobrist::set_stone(board, &mut shared.hash, *point, Color::empty());
+                    captures += 1;
+                }
+                suicided = true;
+            } else {
+                for point in stones {
+                    if points_played.contains(point) {
+                        points_played.retain(|x| x != point);
+                        zobrist::set_stone(board, &mut shared.hash, *point, Color::empty());
+                        // The stone was never there, so seeing it doesn't count as a reveal.
+                        if let Some(visibility) = &mut shared.board_visibility {
+                            let hash = &mut shared.visibility_hash;
+                            zobrist::set_visibility(visibility, hash, *point, Bitmap::new());
+                        }
+                    }
                 }
+                taken_back = true;
             }
-            let reveals = reveal_group(&mut shared.board_visibility, group, board);
+            let reveals = reveal_group(
+                &mut shared.board_visibility,
+                &mut shared.visibility_hash,
+                stones,
+                board,
+            );
             revealed = revealed || reveals;
         }
 
-        (captures, revealed)
+        // Only parts of these chains were removed, so they have to be found again
+        if taken_back {
+            shared.chains = Chains::new(&shared.board);
+        }
+
+        (captures, revealed, suicided)
     }
 
-    /// Superko
-    /// We only need to scan back capture_count boards, as per Ten 1p's clever idea.
-    /// The board can't possibly repeat further back than the number of removed stones.
+    /// Ko, as picked by `GameModifier::ko`.
+    /// For superko we only need to scan back capture_count boards, as per Ten 1p's clever
+    /// idea. The board can't possibly repeat further back than the number of removed stones.
+    /// Positions are compared by their Zobrist hash. Since version 1 of the hidden move
+    /// rules, stones only repeat a position if they are also equally hidden.
     fn superko(
         &self,
         shared: &mut SharedState,
         captures: usize,
-        hash: u64,
+        stones_played: &[Point],
     ) -> MakeActionResult<()> {
-        for BoardHistory {
-            hash: old_hash,
-            board: old_board,
-            ..
-        } in shared
-            .board_history
-            .iter()
-            .rev()
-            .take(shared.capture_count + captures)
-        {
-            if *old_hash == hash && old_board == &shared.board {
-                let BoardHistory {
-                    board: old_board,
-                    points: old_points,
-                    ..
-                } = shared
-                    .board_history
-                    .last()
-                    .expect("board_history.last() shouldn't be None")
-                    .clone();
-                shared.board = old_board;
-                shared.points = old_points;
-                return Err(MakeActionError::Ko);
-            }
+        if repeats_position(shared, shared.hash, shared.visibility_hash, captures) {
+            let BoardHistory {
+                points: old_points,
+                captures: old_captures,
+                ..
+            } = shared
+                .board_history
+                .last()
+                .expect("board_history.last() shouldn't be None")
+                .clone();
+            shared.board = shared.history_tip.board.clone();
+            shared.points = old_points;
+            shared.captures = old_captures;
+            shared.hash = zobrist::hash(&shared.board);
+            shared.chains = Chains::new(&shared.board);
+            forget_placed(shared, stones_played);
+            return Err(MakeActionError::Ko);
         }
 
         Ok(())
@@ -210,16 +235,25 @@ impl PlayState {
             return Ok(ActionChange::None);
         }
 
-        let (captures, revealed) = self.capture(shared, &mut points_played);
+        let stones_played = points_played.clone();
+        let (captures, revealed, suicided) = self.capture(shared, &mut points_played);
 
-        if points_played.is_empty() {
-            let BoardHistory { board, points, .. } = shared
+        if points_played.is_empty() && !suicided {
+            let BoardHistory {
+                points,
+                captures: old_captures,
+                ..
+            } = shared
                 .board_history
                 .last()
                 .expect("board_history.last() shouldn't be None")
                 .clone();
-            shared.board = board;
+            shared.board = shared.history_tip.board.clone();
             shared.points = points;
+            shared.captures = old_captures;
+            shared.hash = zobrist::hash(&shared.board);
+            shared.chains = Chains::new(&shared.board);
+            forget_placed(shared, &stones_played);
 
             if revealed {
                 return Ok(ActionChange::None);
@@ -227,24 +261,9 @@ impl PlayState {
             return Err(MakeActionError::Suicide);
         }
 
-        let hash = shared.board.hash();
-
-        self.superko(shared, captures, hash)?;
+        self.superko(shared, captures, &stones_played)?;
 
-        let new_turn = if let Some(rule) = &shared.mods.n_plus_one {
-            use n_plus_one::NPlusOneResult::*;
-            match n_plus_one::check(
-                &points_played,
-                &shared.board,
-                shared.board_visibility.as_mut(),
-                rule,
-            ) {
-                ExtraTurn => true,
-                Nothing => false,
-            }
-        } else {
-            false
-        };
+        let new_turn = modifiers::extra_turn(shared, &points_played);
 
         if !new_turn {
             shared.turn += 1;
@@ -253,26 +272,32 @@ impl PlayState {
             }
         }
 
-        self.last_stone = Some(points_played);
+        self.ko_point = ko_point(shared, &points_played, captures);
+        self.last_stone = Some(if suicided {
+            stones_played
+        } else {
+            points_played
+        });
         for passed in &mut self.players_passed {
             *passed = false;
         }
 
-        shared.board_history.push(BoardHistory {
-            hash,
-            board: shared.board.clone(),
-            board_visibility: shared.board_visibility.clone(),
-            state: GameState::Play(self.clone()),
-            points: shared.points.clone(),
-            turn: shared.turn,
-        });
+        shared.push_history(GameState::Play(self.clone()));
         shared.capture_count += captures;
 
+        if let Some(team) = modifiers::winner(shared) {
+            shared.result = Some(GameResult::by_captures(team, &shared.captures));
+            return Ok(ActionChange::SwapState(GameState::Done(ScoringState::new(
+                shared,
+            ))));
+        }
+
         Ok(ActionChange::None)
     }
 
     pub fn make_action_pass(&mut self, shared: &mut SharedState) -> MakeActionResult {
         let active_seat = get_active_seat(shared);
+        self.ko_point = None;
 
         for (seat, passed) in shared.seats.iter().zip(self.players_passed.iter_mut()) {
             if seat.team == active_seat.team {
@@ -285,24 +310,13 @@ impl PlayState {
             shared.turn = 0;
         }
 
-        shared.board_history.push(BoardHistory {
-            hash: shared.board.hash(),
-            board: shared.board.clone(),
-            board_visibility: shared.board_visibility.clone(),
-            state: GameState::Play(self.clone()),
-            points: shared.points.clone(),
-            turn: shared.turn,
-        });
+        shared.push_history(GameState::Play(self.clone()));
 
         if self.players_passed.iter().all(|x| *x) {
             for passed in &mut self.players_passed {
                 *passed = false;
             }
-            return Ok(ActionChange::PushState(GameState::scoring(
-                &shared.board,
-                shared.seats.len(),
-                &shared.points,
-            )));
+            return Ok(ActionChange::PushState(GameState::scoring(shared)));
         }
 
         Ok(ActionChange::None)
@@ -314,25 +328,97 @@ impl PlayState {
             return Err(MakeActionError::OutOfBounds);
         }
 
-        shared
-            .board_history
-            .pop()
-            .ok_or(MakeActionError::OutOfBounds)?;
+        shared.pop_history().ok_or(MakeActionError::OutOfBounds)?;
         let history = shared
             .board_history
             .last()
             .ok_or(MakeActionError::OutOfBounds)?;
 
-        shared.board = history.board.clone();
-        shared.board_visibility = history.board_visibility.clone();
+        shared.board = shared.history_tip.board.clone();
+        shared.board_visibility = shared.history_tip.board_visibility.clone();
+        shared.hash = history.hash;
+        shared.visibility_hash = history.visibility_hash;
+        shared.chains = Chains::new(&shared.board);
         shared.points = history.points.clone();
+        shared.captures = history.captures.clone();
         shared.turn = history.turn;
 
         *self = history.state.assume::<PlayState>().clone();
+        self.undo_request = None;
 
         Ok(ActionChange::None)
     }
 
+    fn make_action_request_undo(
+        &mut self,
+        shared: &mut SharedState,
+        player_id: u64,
+    ) -> MakeActionResult {
+        match shared.mods.undo {
+            UndoPolicy::Never => return Err(MakeActionError::UndoNotAllowed),
+            UndoPolicy::Free => return self.make_action_cancel(shared),
+            UndoPolicy::Approval => {}
+        }
+        if self.undo_request.is_some() {
+            return Err(MakeActionError::UndoPending);
+        }
+        // Only the seat that made the move can ask to take it back.
+        let seat = last_mover(shared).ok_or(MakeActionError::OutOfBounds)?;
+        if shared.seats[seat].player != Some(player_id) {
+            return Err(MakeActionError::NotTurn);
+        }
+
+        self.undo_request = Some(UndoRequest {
+            seat,
+            accepted: shared
+                .seats
+                .iter()
+                .map(|x| x.player == Some(player_id))
+                .collect(),
+        });
+        self.finish_undo(shared)
+    }
+
+    fn make_action_answer_undo(
+        &mut self,
+        shared: &mut SharedState,
+        player_id: u64,
+        accept: bool,
+    ) -> MakeActionResult {
+        let request = self
+            .undo_request
+            .as_mut()
+            .ok_or(MakeActionError::NoUndoRequest)?;
+
+        // A player answers for every seat they hold.
+        let mut answered = false;
+        for (seat, accepted) in shared.seats.iter().zip(&mut request.accepted) {
+            if seat.player == Some(player_id) && !*accepted {
+                *accepted = true;
+                answered = true;
+            }
+        }
+        if !answered {
+            return Err(MakeActionError::NotTurn);
+        }
+
+        if !accept {
+            self.undo_request = None;
+            return Ok(ActionChange::None);
+        }
+        self.finish_undo(shared)
+    }
+
+    /// Takes the move back once every seat accepted.
+    fn finish_undo(&mut self, shared: &mut SharedState) -> MakeActionResult {
+        let request = self.undo_request.as_ref().expect("No undo request");
+        if request.accepted.iter().all(|x| *x) {
+            self.make_action_cancel(shared)
+        } else {
+            Ok(ActionChange::None)
+        }
+    }
+
     pub fn make_action(
         &mut self,
         shared: &mut SharedState,
@@ -340,30 +426,43 @@ impl PlayState {
         action: ActionKind,
     ) -> MakeActionResult {
         let active_seat = get_active_seat(shared);
-        if active_seat.player != Some(player_id) {
+        // Resigning and answering undo requests don't have to wait for your turn. Undos that
+        // need approval are asked for by whoever made the move, free ones are a cancel.
+        let any_turn = match action {
+            ActionKind::Resign | ActionKind::AcceptUndo | ActionKind::DeclineUndo => true,
+            ActionKind::RequestUndo => shared.mods.undo != UndoPolicy::Free,
+            _ => false,
+        };
+        if active_seat.player != Some(player_id) && !any_turn {
             return Err(MakeActionError::NotTurn);
         }
 
         let res = match action {
             ActionKind::Place(x, y) => self.make_action_place(shared, (x, y)),
             ActionKind::Pass => self.make_action_pass(shared),
+            ActionKind::Cancel if shared.mods.undo != UndoPolicy::Free => {
+                Err(MakeActionError::UndoNotAllowed)
+            }
             ActionKind::Cancel => self.make_action_cancel(shared),
+            ActionKind::RequestUndo => self.make_action_request_undo(shared, player_id),
+            ActionKind::AcceptUndo => self.make_action_answer_undo(shared, player_id, true),
+            ActionKind::DeclineUndo => self.make_action_answer_undo(shared, player_id, false),
+            ActionKind::Resign => return super::resign(shared, player_id),
         };
 
         let res = res?;
 
-        self.set_zen_teams(shared);
-
-        Ok(res)
-    }
+        if let ActionKind::Place(..) | ActionKind::Pass = action {
+            // The request was for the move before.
+            self.undo_request = None;
+        }
 
-    fn set_zen_teams(&mut self, shared: &mut SharedState) {
-        let move_number = shared.board_history.len() - 1;
-        if let Some(zen) = &shared.mods.zen_go {
-            for seat in &mut shared.seats {
-                seat.team = Color((move_number % zen.color_count as usize) as u8 + 1);
-            }
+        let modifiers = shared.modifiers.clone();
+        for modifier in modifiers.iter() {
+            modifier.after_action(shared);
         }
+
+        Ok(res)
     }
 }
 
@@ -375,23 +474,430 @@ fn get_active_seat(shared: &SharedState) -> Seat {
         .clone()
 }
 
+/// The seat that made the last move, if there is one to take back.
+pub(crate) fn last_mover(shared: &SharedState) -> Option<usize> {
+    shared.board_history.iter().rev().nth(1).map(|h| h.turn)
+}
+
+/// Whether the seat to move making a position with these hashes breaks the ko rule.
+pub(crate) fn repeats_position(
+    shared: &SharedState,
+    hash: u64,
+    visibility_hash: u64,
+    captures: usize,
+) -> bool {
+    // Phantom captures show stones to everyone, which would make every ko retake look like
+    // a new position.
+    let with_visibility = shared.rules.hidden_move >= 1
+        && shared.mods.visibility_mode != Some(VisibilityMode::Phantom);
+    let history = &shared.board_history;
+    let repeats = |idx: usize| {
+        let old = &history[idx];
+        old.hash == hash && (!with_visibility || old.visibility_hash == visibility_hash)
+    };
+    let scanned = history
+        .len()
+        .saturating_sub(shared.capture_count + captures)..history.len();
+
+    match shared.mods.ko {
+        KoRule::Simple => history.len() >= 2 && repeats(history.len() - 2),
+        KoRule::Positional => scanned.rev().any(repeats),
+        // The seat whose turn it was before a position made it. Nobody made the starting
+        // position, so it counts for every seat.
+        KoRule::Situational => scanned
+            .rev()
+            .any(|idx| (idx == 0 || history[idx - 1].turn == shared.turn) && repeats(idx)),
+    }
+}
+
+/// Finds the point the next seat can't play on, if this move took a ko. A ko is taken by
+/// capturing a single stone with a single stone, which is then left in atari.
+fn ko_point(shared: &SharedState, points_played: &[Point], captures: usize) -> Option<Point> {
+    if captures != 1 || points_played.len() != 1 {
+        return None;
+    }
+    let chain = shared.chains.chain_at(points_played[0])?;
+    if chain.stones.len() != 1 || chain.liberties.len() != 1 {
+        return None;
+    }
+
+    // Only the team that lost the stone would be retaking it, not a third color or the
+    // capturer moving again. Zen go only changes the teams after this.
+    let next_team = match &shared.mods.zen_go {
+        Some(zen) => Color((shared.board_history.len() % zen.color_count as usize) as u8 + 1),
+        None => shared.seats[shared.turn].team,
+    };
+    if shared.history_tip.board.get_point(chain.liberties[0]) != next_team {
+        return None;
+    }
+
+    // Retaking would repeat the position from before this move.
+    let history = &shared.board_history;
+    let banned = match shared.mods.ko {
+        KoRule::Simple | KoRule::Positional => true,
+        KoRule::Situational => history.len() < 2 || history[history.len() - 2].turn == shared.turn,
+    };
+    if banned {
+        Some(chain.liberties[0])
+    } else {
+        None
+    }
+}
+
+/// Clears who saw the stones of a move that was taken back, once the board is restored.
+/// Stones the move revealed stay revealed.
+fn forget_placed(shared: &mut SharedState, stones_played: &[Point]) {
+    if let Some(visibility) = &mut shared.board_visibility {
+        for &point in stones_played {
+            if shared.board.get_point(point).is_empty() {
+                let hash = &mut shared.visibility_hash;
+                zobrist::set_visibility(visibility, hash, point, Bitmap::new());
+            }
+        }
+    }
+}
+
 fn reveal_group(
     visibility: &mut Option<VisibilityBoard>,
-    group: &Group,
+    visibility_hash: &mut u64,
+    stones: &[Point],
     board: &Board,
 ) -> Revealed {
     let mut revealed = false;
 
     if let Some(visibility) = visibility {
-        for &point in &group.points {
+        for &point in stones {
             revealed = revealed || !visibility.get_point(point).is_empty();
-            *visibility.point_mut(point) = Bitmap::new();
+            zobrist::set_visibility(visibility, visibility_hash, point, Bitmap::new());
             for point in board.surrounding_points(point) {
                 revealed = revealed || !visibility.get_point(point).is_empty();
-                *visibility.point_mut(point) = Bitmap::new();
+                zobrist::set_visibility(visibility, visibility_hash, point, Bitmap::new());
             }
         }
     }
 
     revealed
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        zobrist, ActionKind, Color, Game, GameModifier, GameState, HiddenMoveGo, KoRule,
+        MakeActionError, NPlusOne, ScoringRules, Setup, UndoPolicy,
+    };
+
+    /// Black can take a white stone at (1, 1) in ko by playing (2, 1).
+    fn ko_game(seats: &[u8], ko: KoRule, turn: u32) -> Game {
+        let mods = GameModifier {
+            ko,
+            ..GameModifier::default()
+        };
+        ko_game_with(seats, mods, turn)
+    }
+
+    fn ko_game_with(seats: &[u8], mods: GameModifier, turn: u32) -> Game {
+        let komis = seats.iter().map(|_| 0).collect();
+        let mut game = Game::standard(seats, komis, (5, 5), mods).unwrap();
+        for seat in 0..seats.len() {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+        let black = [(1, 0), (0, 1), (1, 2)];
+        let white = [(2, 0), (1, 1), (3, 1), (2, 2)];
+        let stones = black
+            .iter()
+            .map(|&p| (p, 1.into()))
+            .chain(white.iter().map(|&p| (p, 2.into())))
+            .collect();
+        game.setup(Setup { stones, turn }).unwrap();
+        game
+    }
+
+    #[test]
+    fn ko_cant_be_retaken_right_away() {
+        for &ko in &[KoRule::Simple, KoRule::Positional, KoRule::Situational] {
+            let mut game = ko_game(&[1, 2], ko, 0);
+            game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+            assert_eq!(
+                game.state.assume::<super::PlayState>().ko_point,
+                Some((1, 1))
+            );
+            assert_eq!(
+                game.make_action(2, ActionKind::Place(1, 1)),
+                Err(MakeActionError::Ko),
+                "{:?}",
+                ko
+            );
+
+            game.make_action(2, ActionKind::Place(4, 4)).unwrap();
+            assert_eq!(game.state.assume::<super::PlayState>().ko_point, None);
+            game.make_action(1, ActionKind::Place(4, 3)).unwrap();
+            game.make_action(2, ActionKind::Place(1, 1)).unwrap();
+        }
+    }
+
+    #[test]
+    fn situational_superko_depends_on_the_seat() {
+        // Red moves first, so white retaking repeats a position red made.
+        for &(ko, allowed) in &[
+            (KoRule::Simple, false),
+            (KoRule::Positional, false),
+            (KoRule::Situational, true),
+        ] {
+            let mut game = ko_game(&[1, 2, 3], ko, 2);
+            game.make_action(3, ActionKind::Place(4, 4)).unwrap();
+            game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+            let ko_point = game.state.assume::<super::PlayState>().ko_point;
+            assert_eq!(ko_point.is_none(), allowed, "{:?}", ko);
+            assert_eq!(
+                game.make_action(2, ActionKind::Place(1, 1)).is_ok(),
+                allowed,
+                "{:?}",
+                ko
+            );
+        }
+    }
+
+    #[test]
+    fn ko_point_is_only_for_the_captured_team() {
+        // Red moves after black and isn't retaking anything.
+        let mut game = ko_game(&[1, 3, 2], KoRule::Simple, 0);
+        game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+        assert_eq!(game.state.assume::<super::PlayState>().ko_point, None);
+        assert!(game.is_legal(2, &ActionKind::Place(1, 1)));
+        game.make_action(2, ActionKind::Place(1, 1)).unwrap();
+        assert_eq!(game.shared.board.get_point((2, 1)), Color::empty());
+
+        // The capture makes two diagonal lines of two, so black moves again.
+        let mods = GameModifier {
+            n_plus_one: Some(NPlusOne { length: 2 }),
+            ..GameModifier::default()
+        };
+        let mut game = ko_game_with(&[1, 2], mods, 0);
+        game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+        assert_eq!(game.shared.turn, 0);
+        assert_eq!(game.state.assume::<super::PlayState>().ko_point, None);
+    }
+
+    /// Black can kill its own two stones by playing (1, 0), or a single stone at (4, 4).
+    fn suicide_game(suicide: bool, scoring: ScoringRules) -> Game {
+        let mods = GameModifier {
+            suicide,
+            scoring,
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (5, 5), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        let white = [(2, 0), (0, 1), (1, 1), (3, 4), (4, 3)];
+        let stones = white
+            .iter()
+            .map(|&p| (p, 2.into()))
+            .chain(Some(((0, 0), 1.into())))
+            .collect();
+        game.setup(Setup { stones, turn: 0 }).unwrap();
+        game
+    }
+
+    #[test]
+    fn multi_stone_suicide_removes_the_chain() {
+        let mut game = suicide_game(false, ScoringRules::Area);
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(1, 0)),
+            Err(MakeActionError::Suicide)
+        );
+
+        let mut game = suicide_game(true, ScoringRules::Area);
+        assert_eq!(
+            game.make_action(1, ActionKind::Place(4, 4)),
+            Err(MakeActionError::Suicide)
+        );
+        game.make_action(1, ActionKind::Place(1, 0)).unwrap();
+        assert_eq!(game.shared.board.get_point((0, 0)), Color::empty());
+        assert_eq!(game.shared.board.get_point((1, 0)), Color::empty());
+        assert_eq!(game.shared.turn, 1);
+        assert_eq!(game.shared.hash, zobrist::hash(&game.shared.board));
+        assert_eq!(
+            game.shared.chains.to_groups().len(),
+            crate::game::find_groups(&game.shared.board).len()
+        );
+    }
+
+    #[test]
+    fn suicided_stones_are_prisoners() {
+        let mut game = suicide_game(true, ScoringRules::Territory);
+        game.make_action(1, ActionKind::Place(1, 0)).unwrap();
+        assert_eq!(&game.shared.captures[..], &[0, 2]);
+
+        game.make_action(2, ActionKind::Pass).unwrap();
+        game.make_action(1, ActionKind::Pass).unwrap();
+        let scores = match &game.state {
+            GameState::Scoring(state) => state.scores.clone(),
+            _ => unreachable!(),
+        };
+        // 20 points of territory and 2 prisoners, in half points.
+        assert_eq!(&scores[..], &[0, 44]);
+    }
+
+    #[test]
+    fn suicide_reveals_hidden_stones() {
+        let mods = GameModifier {
+            suicide: true,
+            hidden_move: Some(HiddenMoveGo {
+                placement_count: 3,
+                teams_share_stones: true,
+            }),
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (5, 5), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        for &(x, y) in &[(0, 0), (4, 4), (4, 3)] {
+            game.make_action(1, ActionKind::Place(x, y)).unwrap();
+        }
+        for &(x, y) in &[(2, 0), (0, 1), (1, 1)] {
+            game.make_action(2, ActionKind::Place(x, y)).unwrap();
+        }
+        game.make_action(1, ActionKind::Pass).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+
+        // Black walks into the hidden white stones, killing two stones.
+        game.make_action(1, ActionKind::Place(1, 0)).unwrap();
+        assert_eq!(game.shared.board.get_point((0, 0)), Color::empty());
+        assert_eq!(game.shared.turn, 1);
+
+        let visibility = game.shared.board_visibility.as_ref().unwrap();
+        for &point in &[(0, 0), (2, 0), (0, 1), (1, 1)] {
+            assert!(visibility.get_point(point).is_empty(), "{:?}", point);
+        }
+        assert!(!visibility.get_point((4, 4)).is_empty());
+        assert_eq!(
+            game.shared.visibility_hash,
+            zobrist::visibility_hash(Some(visibility))
+        );
+    }
+
+    #[test]
+    fn undo_needs_every_other_seat() {
+        let mods = GameModifier {
+            undo: UndoPolicy::Approval,
+            ..GameModifier::default()
+        };
+        let mut game =
+            Game::standard(&[1, 2, 1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        for seat in 0..4 {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(2, ActionKind::Place(6, 6)).unwrap();
+        assert_eq!(
+            game.make_action(3, ActionKind::Cancel),
+            Err(MakeActionError::UndoNotAllowed)
+        );
+
+        // A declined request changes nothing.
+        game.make_action(2, ActionKind::RequestUndo).unwrap();
+        game.make_action(1, ActionKind::DeclineUndo).unwrap();
+        assert_eq!(
+            game.make_action(3, ActionKind::AcceptUndo),
+            Err(MakeActionError::NoUndoRequest)
+        );
+
+        game.make_action(2, ActionKind::RequestUndo).unwrap();
+        assert_eq!(
+            game.make_action(2, ActionKind::AcceptUndo),
+            Err(MakeActionError::NotTurn)
+        );
+        for player in 1..=3 {
+            assert_eq!(game.shared.board.get_point((6, 6)), Color(2));
+            if player != 2 {
+                game.make_action(player, ActionKind::AcceptUndo).unwrap();
+            }
+        }
+        game.make_action(4, ActionKind::AcceptUndo).unwrap();
+        assert_eq!(game.shared.board.get_point((6, 6)), Color::empty());
+        assert_eq!(game.shared.turn, 1);
+        assert_eq!(
+            Game::load(&game.dump()).unwrap().shared.board,
+            game.shared.board
+        );
+
+        let mods = GameModifier {
+            undo: UndoPolicy::Never,
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+        assert_eq!(
+            game.make_action(1, ActionKind::RequestUndo),
+            Err(MakeActionError::UndoNotAllowed)
+        );
+    }
+
+    #[test]
+    fn undo_out_of_turn_is_refused() {
+        let mut game = Game::standard(
+            &[1, 2],
+            tinyvec::tiny_vec![0, 0],
+            (9, 9),
+            GameModifier::default(),
+        )
+        .unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+
+        // Free undos wait for your turn, like a cancel.
+        assert!(!game.is_legal(1, &ActionKind::RequestUndo));
+        assert_eq!(
+            game.make_action(1, ActionKind::RequestUndo),
+            Err(MakeActionError::NotTurn)
+        );
+        assert_eq!(game.shared.board.get_point((2, 2)), Color(1));
+        assert!(game.is_legal(2, &ActionKind::RequestUndo));
+        game.make_action(2, ActionKind::RequestUndo).unwrap();
+        assert_eq!(game.shared.board.get_point((2, 2)), Color::empty());
+
+        let mods = GameModifier {
+            undo: UndoPolicy::Approval,
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+
+        // Only the author of the move can ask to take it back.
+        assert!(!game.is_legal(2, &ActionKind::RequestUndo));
+        assert_eq!(
+            game.make_action(2, ActionKind::RequestUndo),
+            Err(MakeActionError::NotTurn)
+        );
+        assert!(game.is_legal(1, &ActionKind::RequestUndo));
+    }
+
+    #[test]
+    fn undo_request_waits_for_the_pending_one() {
+        let mods = GameModifier {
+            undo: UndoPolicy::Approval,
+            ..GameModifier::default()
+        };
+        let mut game = Game::standard(&[1, 2, 1], tinyvec::tiny_vec![0, 0], (9, 9), mods).unwrap();
+        for seat in 0..3 {
+            game.take_seat(seat as u64 + 1, seat).unwrap();
+        }
+        game.make_action(1, ActionKind::Place(2, 2)).unwrap();
+        game.make_action(1, ActionKind::RequestUndo).unwrap();
+        game.make_action(2, ActionKind::AcceptUndo).unwrap();
+
+        assert!(!game.is_legal(1, &ActionKind::RequestUndo));
+        assert_eq!(
+            game.make_action(1, ActionKind::RequestUndo),
+            Err(MakeActionError::UndoPending)
+        );
+        // The accept already given still counts.
+        game.make_action(3, ActionKind::AcceptUndo).unwrap();
+        assert_eq!(game.shared.board.get_point((2, 2)), Color::empty());
+    }
+}
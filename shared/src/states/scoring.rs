@@ -1,6 +1,6 @@
 use crate::game::{
-    find_groups, ActionChange, ActionKind, Board, Color, GameState, Group, GroupVec,
-    MakeActionResult, Point, SharedState,
+    ActionChange, ActionKind, Board, Chains, Color, GameState, Group, GroupVec, MakeActionError,
+    MakeActionResult, Point, ScoringRules, SharedState,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashSet, VecDeque};
@@ -16,20 +16,18 @@ pub struct ScoringState {
 }
 
 impl ScoringState {
-    pub fn new(board: &Board, seat_count: usize, scores: &[i32]) -> Self {
-        let groups = find_groups(board);
-        let points = score_board(board.width, board.height, &groups);
-        let mut scores: GroupVec<i32> = scores.into();
-        for color in &points.points {
-            if !color.is_empty() {
-                scores[color.0 as usize - 1] += 2;
-            }
+    pub fn new(shared: &SharedState) -> Self {
+        let mut groups = shared.chains.to_groups();
+        if shared.rules.scoring >= 1 {
+            estimate_dead(&shared.board, &mut groups);
         }
+        mark_seki(&shared.board, &mut groups);
+        let (points, scores) = score(shared, &groups);
         ScoringState {
             groups,
             points,
             scores,
-            players_accepted: vec![false; seat_count],
+            players_accepted: vec![false; shared.seats.len()],
         }
     }
 
@@ -47,13 +45,10 @@ impl ScoringState {
 
         group.alive = !group.alive;
 
-        self.points = score_board(shared.board.width, shared.board.height, &self.groups);
-        self.scores = shared.points.clone();
-        for color in &self.points.points {
-            if !color.is_empty() {
-                self.scores[color.0 as usize - 1] += 2;
-            }
-        }
+        mark_seki(&shared.board, &mut self.groups);
+        let (points, scores) = score(shared, &self.groups);
+        self.points = points;
+        self.scores = scores;
 
         for accept in &mut self.players_accepted {
             *accept = false;
@@ -94,15 +89,73 @@ impl ScoringState {
             ActionKind::Place(x, y) => self.make_action_place(shared, (x, y)),
             ActionKind::Pass => self.make_action_pass(shared, player_id),
             ActionKind::Cancel => Ok(ActionChange::PopState),
+            ActionKind::Resign => super::resign(shared, player_id),
+            ActionKind::RequestUndo => Err(MakeActionError::UndoNotAllowed),
+            ActionKind::AcceptUndo | ActionKind::DeclineUndo => Err(MakeActionError::NoUndoRequest),
         }
     }
 }
 
-/// Scores a board by filling in fully surrounded empty spaces based on chinese rules
-fn score_board(width: u32, height: u32, groups: &[Group]) -> Board {
-    let mut board = Board::empty(width, height);
+/// Counts the score of every team with the rules of the game.
+/// Returns the owner of every point as well.
+fn score(shared: &SharedState, groups: &[Group]) -> (Board, GroupVec<i32>) {
+    let board = &shared.board;
+    let live = live_board(board, groups);
+    let points = score_board(live.clone());
+    let mut scores = shared.points.clone();
+
+    match shared.mods.scoring {
+        ScoringRules::Area => {
+            for color in &points.points {
+                if !color.is_empty() {
+                    scores[color.0 as usize - 1] += 2;
+                }
+            }
+        }
+        ScoringRules::Territory => {
+            let mut seki = board.blank::<bool>();
+            for group in groups.iter().filter(|g| g.seki) {
+                for &point in &group.points {
+                    *seki.point_mut(point) = true;
+                }
+            }
+
+            for region in empty_regions(&live) {
+                let owner = points.get_point(region[0]);
+                if owner.is_empty() {
+                    continue;
+                }
+
+                // Eyes of groups in seki are not territory.
+                let in_seki = region
+                    .iter()
+                    .flat_map(|&p| live.surrounding_points(p))
+                    .any(|p| seki.get_point(p));
+                if in_seki {
+                    continue;
+                }
+
+                for &point in &region {
+                    scores[owner.0 as usize - 1] += 2;
+                    // Dead stones are taken as prisoners
+                    if !board.get_point(point).is_empty() {
+                        scores[owner.0 as usize - 1] += 2;
+                    }
+                }
+            }
+
+            for (score, captures) in scores.iter_mut().zip(&shared.captures) {
+                *score += 2 * captures;
+            }
+        }
+    }
+
+    (points, scores)
+}
 
-    // Fill living groups to the board
+/// Builds a board with only the living groups on it.
+fn live_board(board: &Board, groups: &[Group]) -> Board {
+    let mut board = board.blank();
     for group in groups {
         if !group.alive {
             continue;
@@ -111,7 +164,129 @@ fn score_board(width: u32, height: u32, groups: &[Group]) -> Board {
             *board.point_mut(*point) = group.team;
         }
     }
+    board
+}
+
+/// Marks the living groups which are in seki. Dead groups are taken off the board
+/// first, since they can't hold anyone in seki.
+fn mark_seki(board: &Board, groups: &mut [Group]) {
+    let live = live_board(board, groups);
+    let seki = find_seki(&live);
+    for group in groups {
+        group.seki = group.alive && seki.get_point(group.points[0]);
+    }
+}
+
+/// Finds every connected region of empty points.
+fn empty_regions(board: &Board) -> Vec<Vec<Point>> {
+    let mut seen = board.blank::<bool>();
+    let mut regions = Vec::new();
+
+    for idx in 0..board.points.len() {
+        let start = board.idx_to_coord(idx).expect("Index within board");
+        if !board.get_point(start).is_empty() || seen.get_point(start) {
+            continue;
+        }
+
+        *seen.point_mut(start) = true;
+        let mut region = vec![start];
+        let mut i = 0;
+        while let Some(&point) = region.get(i) {
+            for p in board.surrounding_points(point) {
+                if board.get_point(p).is_empty() && !seen.get_point(p) {
+                    *seen.point_mut(p) = true;
+                    region.push(p);
+                }
+            }
+            i += 1;
+        }
+        regions.push(region);
+    }
+
+    regions
+}
+
+/// Finds the stones of groups in seki. A group is in seki when it is next to a shared
+/// liberty which no adjacent color can fill without putting itself in atari.
+fn find_seki(live: &Board) -> Board<bool> {
+    let mut seki = live.blank::<bool>();
+    let chains = Chains::new(live);
+
+    for region in empty_regions(live) {
+        let colors = region
+            .iter()
+            .flat_map(|&p| live.surrounding_points(p))
+            .map(|p| live.get_point(p))
+            .filter(|c| !c.is_empty())
+            .collect::<HashSet<_>>();
+        if colors.len() < 2 {
+            continue;
+        }
+
+        for &point in &region {
+            let neighbours = live
+                .surrounding_points(point)
+                .filter(|&p| !live.get_point(p).is_empty())
+                .collect::<Vec<_>>();
+            if neighbours.is_empty() {
+                continue;
+            }
+
+            let fillable = neighbours
+                .iter()
+                .any(|&p| fill_is_safe(live, &chains, point, live.get_point(p)));
+            if fillable {
+                continue;
+            }
+
+            for &p in &neighbours {
+                let chain = chains.chain_at(p).expect("Stones are in a chain");
+                for &stone in &chain.stones {
+                    *seki.point_mut(stone) = true;
+                }
+            }
+        }
+    }
+
+    seki
+}
+
+/// Checks if `color` can play at `point` without being in atari afterwards. `chains` are
+/// the chains of `board`, before the stone.
+fn fill_is_safe(board: &Board, chains: &Chains, point: Point, color: Color) -> bool {
+    let mut liberties = GroupVec::<Point>::new();
+    let mut add = |p: Point| {
+        if p != point && !liberties.contains(&p) {
+            liberties.push(p);
+        }
+        liberties.len() >= 2
+    };
+
+    for p in board.surrounding_points(point) {
+        let c = board.get_point(p);
+        if c.is_empty() {
+            if add(p) {
+                return true;
+            }
+            continue;
+        }
+
+        let chain = chains.chain_at(p).expect("Stones are in a chain");
+        if c != color {
+            // Filling the last liberty of another color captures it.
+            if chain.liberties.len() == 1 {
+                return true;
+            }
+        } else if chain.liberties.iter().any(|&l| add(l)) {
+            return true;
+        }
+    }
+    false
+}
 
+/// Scores a board by filling in fully surrounded empty spaces.
+/// The board should only contain living groups.
+fn score_board(mut board: Board) -> Board {
     // Find empty points
     let mut legal_points = board
         .points
@@ -180,3 +355,411 @@ fn score_board(width: u32, height: u32, groups: &[Group]) -> Board {
 
     board
 }
+
+// Dead stone estimation ////
+
+/// Distance at which a stone stops having influence on a point.
+const INFLUENCE_RADIUS: i32 = 4;
+
+/// Guesses which groups are dead so players don't have to mark each one by hand.
+///
+/// Groups that are unconditionally alive (Benson's algorithm), in seki or that have
+/// two eyes or a larger eye space are kept alive. The remaining groups are marked dead
+/// when another color has more influence over their stones and liberties.
+pub fn estimate_dead(board: &Board, groups: &mut [Group]) {
+    let colors = groups.iter().map(|g| g.team).collect::<HashSet<_>>();
+
+    let mut safe = find_seki(board);
+    for &color in &colors {
+        for (idx, alive) in benson_alive(board, color).points.iter().enumerate() {
+            if *alive {
+                safe.points[idx] = true;
+            }
+        }
+    }
+
+    let regions = empty_regions(board);
+    let region_owners = regions
+        .iter()
+        .map(|region| {
+            let colors = region
+                .iter()
+                .flat_map(|&p| board.surrounding_points(p))
+                .map(|p| board.get_point(p))
+                .filter(|c| !c.is_empty())
+                .collect::<HashSet<_>>();
+            match colors.len() {
+                1 => colors.into_iter().next(),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut region_of = board.blank::<usize>();
+    for (idx, region) in regions.iter().enumerate() {
+        for &point in region {
+            *region_of.point_mut(point) = idx;
+        }
+    }
+
+    let influence = influence_map(board, &colors);
+
+    for group in groups {
+        if safe.get_point(group.points[0]) {
+            continue;
+        }
+
+        let liberties = group
+            .points
+            .iter()
+            .flat_map(|&p| board.surrounding_points(p))
+            .filter(|&p| board.get_point(p).is_empty())
+            .collect::<HashSet<_>>();
+
+        let eyes = liberties
+            .iter()
+            .map(|&p| region_of.get_point(p))
+            .filter(|&r| region_owners[r] == Some(group.team))
+            .collect::<HashSet<_>>();
+        let eye_space = eyes.iter().map(|&r| regions[r].len()).sum::<usize>();
+        if eyes.len() >= 2 || eye_space >= 4 {
+            continue;
+        }
+
+        let area = group.points.iter().chain(liberties.iter());
+        let mut totals = influence
+            .iter()
+            .map(|(color, board)| (*color, area.clone().map(|&p| board.get_point(p)).sum()))
+            .collect::<Vec<(Color, i32)>>();
+        totals.sort_by_key(|(_, total)| -total);
+
+        let own = totals
+            .iter()
+            .find(|(c, _)| *c == group.team)
+            .map_or(0, |(_, t)| *t);
+        let enemy = totals
+            .iter()
+            .find(|(c, _)| *c != group.team)
+            .map_or(0, |(_, t)| *t);
+        if enemy > own {
+            group.alive = false;
+        }
+    }
+}
+
+/// Sums up the influence of every stone on the points around it for each color.
+/// Influence falls off linearly with the number of steps from the stone.
+fn influence_map(board: &Board, colors: &HashSet<Color>) -> Vec<(Color, Board<i32>)> {
+    let mut maps = colors
+        .iter()
+        .map(|&c| (c, board.blank::<i32>()))
+        .collect::<Vec<_>>();
+
+    let mut reached = Vec::new();
+    for (idx, &color) in board.points.iter().enumerate() {
+        let map = match maps.iter_mut().find(|(c, _)| *c == color) {
+            Some((_, map)) => map,
+            None => continue,
+        };
+        reached.clear();
+        reached.push(board.idx_to_coord(idx).expect("Index within board"));
+        let mut start = 0;
+        for distance in 0..INFLUENCE_RADIUS {
+            let end = reached.len();
+            for i in start..end {
+                *map.point_mut(reached[i]) += INFLUENCE_RADIUS - distance;
+                for p in board.surrounding_points(reached[i]) {
+                    if !reached.contains(&p) {
+                        reached.push(p);
+                    }
+                }
+            }
+            start = end;
+        }
+    }
+
+    maps
+}
+
+/// Finds the stones of `color` which are unconditionally alive with Benson's algorithm.
+/// Everything that isn't `color` counts as a region, so this works with any number of
+/// colors.
+fn benson_alive(board: &Board, color: Color) -> Board<bool> {
+    let mut chain_of = board.blank::<Option<usize>>();
+    let mut chains = Vec::new();
+    let mut region_of = board.blank::<Option<usize>>();
+    let mut regions = Vec::new();
+
+    for idx in 0..board.points.len() {
+        let start = board.idx_to_coord(idx).expect("Index within board");
+        let is_chain = board.get_point(start) == color;
+        let (ids, list) = if is_chain {
+            (&mut chain_of, &mut chains)
+        } else {
+            (&mut region_of, &mut regions)
+        };
+        if ids.get_point(start).is_some() {
+            continue;
+        }
+
+        let id = list.len();
+        *ids.point_mut(start) = Some(id);
+        let mut points = vec![start];
+        let mut i = 0;
+        while let Some(&point) = points.get(i) {
+            for p in board.surrounding_points(point) {
+                if (board.get_point(p) == color) == is_chain && ids.get_point(p).is_none() {
+                    *ids.point_mut(p) = Some(id);
+                    points.push(p);
+                }
+            }
+            i += 1;
+        }
+        list.push(points);
+    }
+
+    // Chains bordering each region, and the chains each region is vital to. A region is
+    // vital to a chain if all of its empty points are liberties of the chain.
+    let mut bordering = Vec::with_capacity(regions.len());
+    let mut vital_to = Vec::with_capacity(regions.len());
+    for region in &regions {
+        let border = region
+            .iter()
+            .flat_map(|&p| board.surrounding_points(p))
+            .filter_map(|p| chain_of.get_point(p))
+            .collect::<HashSet<_>>();
+        let empty = region
+            .iter()
+            .filter(|&&p| board.get_point(p).is_empty())
+            .collect::<Vec<_>>();
+        let vital = border
+            .iter()
+            .copied()
+            .filter(|&chain| {
+                !empty.is_empty()
+                    && empty.iter().all(|&&p| {
+                        board
+                            .surrounding_points(p)
+                            .any(|n| chain_of.get_point(n) == Some(chain))
+                    })
+            })
+            .collect::<Vec<_>>();
+        bordering.push(border);
+        vital_to.push(vital);
+    }
+
+    let mut chain_alive = vec![true; chains.len()];
+    let mut region_alive = vec![true; regions.len()];
+    loop {
+        let mut changed = false;
+
+        for (chain, alive) in chain_alive.iter_mut().enumerate() {
+            if !*alive {
+                continue;
+            }
+            let vital_count = (0..regions.len())
+                .filter(|&r| region_alive[r] && vital_to[r].contains(&chain))
+                .count();
+            if vital_count < 2 {
+                *alive = false;
+                changed = true;
+            }
+        }
+
+        for (region, alive) in region_alive.iter_mut().enumerate() {
+            if *alive && bordering[region].iter().any(|&c| !chain_alive[c]) {
+                *alive = false;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut result = board.blank::<bool>();
+    for (chain, points) in chains.iter().enumerate() {
+        if chain_alive[chain] {
+            for &point in points {
+                *result.point_mut(point) = true;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, GameModifier, Setup};
+
+    fn game(rows: &[&str], scoring: ScoringRules) -> Game {
+        let mods = GameModifier {
+            scoring,
+            ..GameModifier::default()
+        };
+        let size = (rows[0].len() as u8, rows.len() as u8);
+        let (teams, komis) = if rows.iter().any(|r| r.contains('R')) {
+            (&[1, 2, 3][..], tinyvec::tiny_vec![0, 0, 0])
+        } else {
+            (&[1, 2][..], tinyvec::tiny_vec![0, 0])
+        };
+        let mut game = Game::standard(teams, komis, size, mods).unwrap();
+        let mut stones = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'B' => stones.push(((x as u32, y as u32), Color(1))),
+                    'W' => stones.push(((x as u32, y as u32), Color(2))),
+                    'R' => stones.push(((x as u32, y as u32), Color(3))),
+                    _ => {}
+                }
+            }
+        }
+        game.setup(Setup { stones, turn: 0 }).unwrap();
+        game
+    }
+
+    // Both middle groups have an eye and share the liberty at e3.
+    const SEKI: &[&str] = &[
+        ".WB.BW.WB.", //
+        "WWBBBWWWBB",
+        ".WBB.WWWB.",
+    ];
+
+    #[test]
+    fn area_scoring_counts_seki_eyes() {
+        let game = game(SEKI, ScoringRules::Area);
+        let state = ScoringState::new(&game.shared);
+        assert_eq!(&state.scores[..], &[28, 30]);
+        assert!(state.points.get_point((4, 2)).is_empty());
+    }
+
+    fn seki_stones(state: &ScoringState) -> Vec<Point> {
+        let mut stones = state
+            .groups
+            .iter()
+            .filter(|g| g.seki)
+            .flat_map(|g| g.points.iter().copied())
+            .collect::<Vec<_>>();
+        stones.sort_unstable();
+        stones
+    }
+
+    #[test]
+    fn seki_groups_are_marked() {
+        let mut game = game(SEKI, ScoringRules::Area);
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Pass).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+
+        let state = game.state.assume::<ScoringState>();
+        let stones = seki_stones(state);
+        assert_eq!(stones.len(), 15);
+        assert!(stones.contains(&(4, 0)) && stones.contains(&(5, 0)));
+        assert!(!stones.contains(&(1, 0)) && !stones.contains(&(8, 0)));
+
+        // Once one side is dead there's no seki left.
+        game.make_action(1, ActionKind::Place(5, 0)).unwrap();
+        let state = game.state.assume::<ScoringState>();
+        assert!(seki_stones(state).is_empty());
+    }
+
+    #[test]
+    fn seki_between_two_of_three_colors() {
+        let rows = &[
+            ".WB.BR.RB.", //
+            "WWBBBRRRBB",
+            ".WBB.RRRB.",
+        ];
+        let game = game(rows, ScoringRules::Territory);
+        let state = ScoringState::new(&game.shared);
+        let teams = state
+            .groups
+            .iter()
+            .filter(|g| g.seki)
+            .map(|g| g.team)
+            .collect::<Vec<_>>();
+        assert_eq!(teams.len(), 2);
+        assert!(teams.contains(&Color(1)) && teams.contains(&Color(3)));
+        // Only the outer groups get territory.
+        assert_eq!(&state.scores[..], &[4, 4, 0]);
+    }
+
+    #[test]
+    fn territory_scoring_skips_seki_eyes() {
+        let game = game(SEKI, ScoringRules::Territory);
+        let state = ScoringState::new(&game.shared);
+        assert_eq!(&state.scores[..], &[4, 4]);
+    }
+
+    #[test]
+    fn territory_scoring_counts_prisoners() {
+        let rows = &[
+            "...BW.", //
+            "...BW.", "BBBBW.", "BBBBW.",
+        ];
+        let mut game = game(rows, ScoringRules::Territory);
+        game.take_seat(1, 0).unwrap();
+        game.take_seat(2, 1).unwrap();
+        game.make_action(1, ActionKind::Pass).unwrap();
+        game.make_action(2, ActionKind::Place(0, 0)).unwrap();
+        game.make_action(1, ActionKind::Place(1, 0)).unwrap();
+        game.make_action(2, ActionKind::Place(2, 1)).unwrap();
+        game.make_action(1, ActionKind::Place(0, 1)).unwrap();
+        game.make_action(2, ActionKind::Pass).unwrap();
+        game.make_action(1, ActionKind::Pass).unwrap();
+        assert_eq!(&game.shared.captures[..], &[1, 0]);
+
+        // The white stone inside black's area is estimated dead, so it is a prisoner
+        // and its area is territory.
+        let state = game.state.assume::<ScoringState>();
+        assert_eq!(&state.scores[..], &[12, 8]);
+
+        // Black: one corner point and one prisoner. White: the right column.
+        game.make_action(1, ActionKind::Place(2, 1)).unwrap();
+        let state = game.state.assume::<ScoringState>();
+        assert_eq!(&state.scores[..], &[4, 8]);
+    }
+
+    fn dead_groups(rows: &[&str]) -> Vec<Point> {
+        let game = game(rows, ScoringRules::Area);
+        let state = ScoringState::new(&game.shared);
+        let mut dead = state
+            .groups
+            .iter()
+            .filter(|g| !g.alive)
+            .flat_map(|g| g.points.iter().copied())
+            .collect::<Vec<_>>();
+        dead.sort_unstable();
+        dead
+    }
+
+    #[test]
+    fn benson_finds_two_eyed_groups() {
+        let rows = &[
+            ".B.BW", //
+            "BBBBW", "WWWWW", ".....",
+        ];
+        let game = game(rows, ScoringRules::Area);
+        let black = benson_alive(&game.shared.board, Color(1));
+        let white = benson_alive(&game.shared.board, Color(2));
+        assert!(black.get_point((0, 1)));
+        assert!(!white.get_point((0, 2)));
+    }
+
+    #[test]
+    fn estimate_marks_invaders_dead() {
+        let rows = &[
+            "..B.W...", //
+            ".WB.W...", "BBB.W.B.", "...WW...", "WWWW....",
+        ];
+        assert_eq!(dead_groups(rows), vec![(1, 1), (6, 2)]);
+    }
+
+    #[test]
+    fn estimate_keeps_seki_alive() {
+        assert_eq!(dead_groups(SEKI), vec![]);
+    }
+}
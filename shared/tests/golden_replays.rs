@@ -0,0 +1,97 @@
+//! Stored replays of every format version, with a summary of how each game ended up.
+//! Loading them must keep giving the same result, whatever happens to the rules.
+
+use shared::game::{Game, GameState};
+
+fn summary(game: &Game) -> String {
+    use std::fmt::Write;
+
+    let shared = &game.shared;
+    let mut out = String::new();
+    let state = match &game.state {
+        GameState::FreePlacement(_) => "free placement",
+        GameState::Play(_) => "play",
+        GameState::Scoring(_) => "scoring",
+        GameState::Done(_) => "done",
+    };
+    writeln!(out, "state: {}", state).unwrap();
+    writeln!(out, "turn: {}", shared.turn).unwrap();
+    writeln!(out, "move: {}", shared.board_history.len() - 1).unwrap();
+    writeln!(out, "points: {:?}", &shared.points[..]).unwrap();
+    if let GameState::Scoring(scoring) | GameState::Done(scoring) = &game.state {
+        writeln!(out, "scores: {:?}", &scoring.scores[..]).unwrap();
+        let mut dead = scoring
+            .groups
+            .iter()
+            .filter(|g| !g.alive)
+            .flat_map(|g| g.points.iter().copied())
+            .collect::<Vec<_>>();
+        dead.sort_unstable();
+        writeln!(out, "dead: {:?}", dead).unwrap();
+    }
+    writeln!(out, "board:").unwrap();
+    for row in shared.board.points.chunks(shared.board.width as usize) {
+        for color in row {
+            match color.0 {
+                0 => out.push('.'),
+                c => write!(out, "{}", c).unwrap(),
+            }
+        }
+        out.push('\n');
+    }
+    if let Some(visibility) = &shared.board_visibility {
+        writeln!(out, "hidden:").unwrap();
+        for row in visibility.points.chunks(visibility.width as usize) {
+            for bits in row {
+                match bits.into_value() {
+                    0 => out.push('.'),
+                    v => write!(out, "{:x}", v).unwrap(),
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn check(name: &str) {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/replays/");
+    let dump = std::fs::read(format!("{}{}.replay", dir, name)).unwrap();
+    let expected = std::fs::read_to_string(format!("{}{}.txt", dir, name)).unwrap();
+
+    let game = Game::load(&dump).unwrap_or_else(|| panic!("{} failed to load", name));
+    assert_eq!(summary(&game), expected, "{} changed", name);
+}
+
+#[test]
+fn unversioned_replays() {
+    // Dead stones were not estimated, the invaders were marked dead by hand.
+    check("v0_marked_dead");
+    // Superko ignored hidden stones and scoring started with every stone alive.
+    check("v0_hidden_move");
+    check("v0_three_colors");
+}
+
+#[test]
+fn version_1_replays() {
+    check("v1_marked_dead");
+    check("v1_hidden_move");
+    check("v1_three_colors");
+}
+
+#[test]
+fn dumps_load_the_same() {
+    // New modifiers add fields to dumps, but reloading a dump must give the same game.
+    for name in &[
+        "v0_hidden_move",
+        "v1_marked_dead",
+        "v1_hidden_move",
+        "v1_three_colors",
+    ] {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/replays/");
+        let dump = std::fs::read(format!("{}{}.replay", dir, name)).unwrap();
+        let game = Game::load(&dump).unwrap();
+        let reloaded = Game::load(&game.dump()).unwrap();
+        assert_eq!(summary(&reloaded), summary(&game), "{} changed", name);
+    }
+}